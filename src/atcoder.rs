@@ -0,0 +1,793 @@
+//! Fetching contests from AtCoder.
+//!
+//! Pages are scraped with plain string searches rather than a full HTML parser: only the
+//! task list table and the sample `<pre>` blocks are needed, and their markup is simple.
+
+use anyhow::{bail, Context, Result};
+use std::{cell::RefCell, collections::BTreeMap, time::Duration};
+
+use crate::runner::{parse_duration, parse_memory};
+
+/// Where AtCoder lives unless overridden with `--base-url` / `KP_BASE_URL`.
+pub const DEFAULT_BASE_URL: &str = "https://atcoder.jp";
+
+/// How long a single request may take.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// HTTP access to AtCoder (or a server mimicking it).
+pub struct Client {
+    base_url: String,
+    agent: ureq::Agent,
+    /// For POSTs, whose redirect tells whether they were accepted.
+    no_redirect: ureq::Agent,
+    /// Value of the `REVEL_SESSION` cookie, kept up to date with what the site sends.
+    session: RefCell<Option<String>>,
+}
+
+/// A row of a contest's task list.
+pub struct TaskInfo {
+    /// e.g. "abc300_a"
+    pub id: String,
+    /// e.g. "A"
+    pub label: String,
+    pub title: String,
+    pub url: String,
+    pub time_limit: Option<Duration>,
+    /// In bytes.
+    pub memory_limit: Option<u64>,
+}
+
+/// What kp takes from the page of a task.
+pub struct TaskPage {
+    pub samples: Vec<Sample>,
+    pub score: Option<u64>,
+}
+
+/// What is needed to fill in the submit form of a contest.
+pub struct SubmitForm {
+    pub csrf_token: String,
+    /// `(id, name)` of each selectable language, e.g. `("5054", "Rust (rustc 1.70.0)")`.
+    pub languages: Vec<(String, String)>,
+}
+
+/// Judging state of a submission, as shown in the submission list.
+pub struct SubmissionStatus {
+    /// e.g. "WJ", "3/20 AC", "AC", "TLE"
+    pub status: String,
+    /// e.g. "23 ms"; only once judged.
+    pub time: Option<String>,
+    /// e.g. "3800 KB"; only once judged.
+    pub memory: Option<String>,
+    pub score: Option<String>,
+    /// How long the site asks to wait before asking again; `None` once judging is over.
+    pub interval: Option<Duration>,
+}
+
+impl SubmissionStatus {
+    /// Whether `status` is a final verdict rather than a waiting or in-progress state.
+    pub fn is_final(&self) -> bool {
+        const VERDICTS: [&str; 9] = ["AC", "WA", "TLE", "MLE", "RE", "CE", "OLE", "IE", "QLE"];
+        VERDICTS.contains(&self.status.as_str())
+    }
+}
+
+/// A sample case of a task.
+pub struct Sample {
+    pub input: String,
+    pub output: String,
+}
+
+impl Client {
+    pub fn new(base_url: &str) -> Client {
+        let builder = || {
+            ureq::AgentBuilder::new()
+                .user_agent(concat!("kp/", env!("CARGO_PKG_VERSION")))
+                .timeout(REQUEST_TIMEOUT)
+        };
+        Client {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            agent: builder().build(),
+            no_redirect: builder().redirects(0).build(),
+            session: RefCell::new(None),
+        }
+    }
+
+    /// Send the session cookie `session` with every request.
+    pub fn with_session(self, session: Option<String>) -> Client {
+        *self.session.borrow_mut() = session;
+        self
+    }
+
+    /// The current session cookie.
+    pub fn session(&self) -> Option<String> {
+        self.session.borrow().clone()
+    }
+
+    fn request(&self, agent: &ureq::Agent, method: &str, url: &str) -> ureq::Request {
+        let request = agent.request(method, url);
+        match &*self.session.borrow() {
+            Some(session) => request.set("Cookie", &format!("{SESSION_COOKIE}={session}")),
+            None => request,
+        }
+    }
+
+    /// Take over a new session cookie set by `response`.
+    fn remember_session(&self, response: &ureq::Response) {
+        for cookie in response.all("Set-Cookie") {
+            let value = cookie.split(';').next().unwrap_or_default();
+            if let Some(session) = value.strip_prefix(&format!("{SESSION_COOKIE}=")) {
+                *self.session.borrow_mut() = Some(session.to_string());
+            }
+        }
+    }
+
+    /// Absolute URL of `path`, which starts with `/`.
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    /// Fetch `url` and return the body.
+    pub fn get(&self, url: &str) -> Result<String> {
+        Ok(self.get_page(url)?.1)
+    }
+
+    /// Fetch `url`, following redirects; returns the final URL and the body.
+    fn get_page(&self, url: &str) -> Result<(String, String)> {
+        match self.request(&self.agent, "GET", url).call() {
+            Ok(response) => {
+                self.remember_session(&response);
+                let final_url = response.get_url().to_string();
+                let body = response
+                    .into_string()
+                    .with_context(|| format!("Failed to read {url}"))?;
+                Ok((final_url, body))
+            }
+            Err(ureq::Error::Status(code, _)) => bail!("GET {} returned {}", url, code),
+            Err(err) => Err(err).with_context(|| format!("GET {url} failed")),
+        }
+    }
+
+    /// POST a form to `url` without following the redirect; returns the status and the
+    /// redirect target, if any.
+    fn post_form(&self, url: &str, fields: &[(&str, &str)]) -> Result<(u16, Option<String>)> {
+        match self
+            .request(&self.no_redirect, "POST", url)
+            .send_form(fields)
+        {
+            Ok(response) => {
+                self.remember_session(&response);
+                Ok((
+                    response.status(),
+                    response.header("Location").map(str::to_string),
+                ))
+            }
+            Err(ureq::Error::Status(code, _)) => bail!("POST {} returned {}", url, code),
+            Err(err) => Err(err).with_context(|| format!("POST {url} failed")),
+        }
+    }
+
+    /// Title and tasks of `contest`.
+    pub fn contest(&self, contest: &str) -> Result<(String, Vec<TaskInfo>)> {
+        let url = self.url(&format!("/contests/{contest}/tasks"));
+        let html = self.get(&url).with_context(|| {
+            format!("Failed to fetch the tasks of {contest} (does it exist and has it started?)")
+        })?;
+        let title = page_title(&html)
+            .map(|t| t.trim_start_matches("Tasks - ").to_string())
+            .unwrap_or_else(|| contest.to_string());
+        let tasks = parse_task_list(&html, &self.base_url);
+        if tasks.is_empty() {
+            bail!("no tasks found at {}", url);
+        }
+        Ok((title, tasks))
+    }
+
+    /// Samples and score shown on the page of `task`.
+    pub fn task_page(&self, task: &TaskInfo) -> Result<TaskPage> {
+        let html = self.get(&task.url)?;
+        Ok(TaskPage {
+            samples: parse_samples(&html),
+            score: parse_score(&html),
+        })
+    }
+
+    /// Log in with the login form; the new session is then available from [`Self::session`].
+    pub fn login(&self, username: &str, password: &str) -> Result<()> {
+        let url = self.url("/login");
+        // Start from a fresh session: the CSRF token is tied to the one the form comes with.
+        *self.session.borrow_mut() = None;
+        let response = match self.request(&self.no_redirect, "GET", &url).call() {
+            Ok(response) => response,
+            Err(ureq::Error::Status(code, _)) => bail!("GET {} returned {}", url, code),
+            Err(err) => return Err(err).with_context(|| format!("GET {url} failed")),
+        };
+        self.remember_session(&response);
+        let html = response.into_string()?;
+        let csrf_token =
+            parse_csrf_token(&html).with_context(|| format!("no login form on {url}"))?;
+
+        let fields = [
+            ("username", username),
+            ("password", password),
+            ("csrf_token", csrf_token.as_str()),
+        ];
+        // A failed login redirects back to the form.
+        let (status, location) = self.post_form(&url, &fields)?;
+        match location {
+            Some(location) if !location.contains("/login") => Ok(()),
+            _ => bail!(
+                "login failed (status {}); check the username and password",
+                status
+            ),
+        }
+    }
+
+    /// Name of the user the session belongs to, or `None` if it is not logged in.
+    pub fn whoami(&self) -> Result<Option<String>> {
+        let (_, html) = self.get_page(&self.url("/home"))?;
+        let marker = "userScreenName = \"";
+        let name = html
+            .find(marker)
+            .and_then(|at| {
+                let rest = &html[at + marker.len()..];
+                Some(rest[..rest.find('"')?].to_string())
+            })
+            .context("cannot tell the user from the page")?;
+        Ok((!name.is_empty()).then_some(name))
+    }
+
+    /// CSRF token and languages of the submit form of `contest`.
+    pub fn submit_form(&self, contest: &str) -> Result<SubmitForm> {
+        let url = self.url(&format!("/contests/{contest}/submit"));
+        let (final_url, html) = self.get_page(&url)?;
+        if final_url.contains("/login") {
+            bail!("not logged in to {}; {}", self.base_url, LOGIN_HINT);
+        }
+        let csrf_token = parse_csrf_token(&html)
+            .with_context(|| format!("no CSRF token on {url}; {LOGIN_HINT}"))?;
+        let languages = parse_languages(&html);
+        if languages.is_empty() {
+            bail!("no language choices on {}", url);
+        }
+        Ok(SubmitForm {
+            csrf_token,
+            languages,
+        })
+    }
+
+    /// Submit `source` for the task `task_id`; returns the ID of the new submission.
+    pub fn submit(
+        &self,
+        contest: &str,
+        task_id: &str,
+        language_id: &str,
+        source: &str,
+        csrf_token: &str,
+    ) -> Result<u64> {
+        let url = self.url(&format!("/contests/{contest}/submit"));
+        let fields = [
+            ("data.TaskScreenName", task_id),
+            ("data.LanguageId", language_id),
+            ("sourceCode", source),
+            ("csrf_token", csrf_token),
+        ];
+        // An accepted submission redirects to the list of our submissions.
+        let (status, location) = self.post_form(&url, &fields)?;
+        match location {
+            Some(location) if location.contains("/submissions/me") => {}
+            _ => bail!(
+                "the submission was not accepted (status {}, redirected to {})",
+                status,
+                location.as_deref().unwrap_or("nowhere")
+            ),
+        }
+
+        let list_url = self.url(&format!("/contests/{contest}/submissions/me"));
+        let html = self.get(&list_url)?;
+        let prefix = format!("/contests/{contest}/submissions/");
+        html.match_indices(&prefix)
+            .find_map(|(start, _)| {
+                let rest = &html[start + prefix.len()..];
+                let end = rest.find(|c: char| !c.is_ascii_digit())?;
+                rest[..end].parse().ok()
+            })
+            .with_context(|| format!("submitted, but no submission found on {list_url}"))
+    }
+
+    /// Current state of our submission `id` in `contest`.
+    pub fn submission_status(&self, contest: &str, id: u64) -> Result<SubmissionStatus> {
+        let url = self.url(&format!(
+            "/contests/{contest}/submissions/me/status/json?reload=true&sids%5B%5D={id}"
+        ));
+        parse_submission_status(&self.get(&url)?, id).with_context(|| format!("from {url}"))
+    }
+}
+
+/// State of submission `id` in a response of the submission status JSON.
+fn parse_submission_status(json: &str, id: u64) -> Result<SubmissionStatus> {
+    let body: serde_json::Value = serde_json::from_str(json).context("not JSON")?;
+    let result = &body["Result"][id.to_string()];
+    let html = result["Html"]
+        .as_str()
+        .with_context(|| format!("submission {id} is not in the status"))?;
+    // The row is a verdict label, followed by time and memory cells once judged.
+    let mut cells = html.split("<td").skip(1).map(|cell| {
+        let cell = cell.split_once('>').map_or("", |(_, rest)| rest);
+        text(cell)
+    });
+    let status = cells.next().unwrap_or_default();
+    let (time, memory) = (cells.next(), cells.next());
+    Ok(SubmissionStatus {
+        status,
+        time,
+        memory,
+        score: result["Score"].as_str().map(str::to_string),
+        interval: body["Interval"].as_u64().map(Duration::from_millis),
+    })
+}
+
+/// Name of AtCoder's session cookie.
+const SESSION_COOKIE: &str = "REVEL_SESSION";
+
+/// How to get a session, for errors about missing ones.
+const LOGIN_HINT: &str = "run `kp login` (the session may have expired)";
+
+/// Value of the `csrf_token` hidden input.
+fn parse_csrf_token(html: &str) -> Option<String> {
+    let at = html.find("name=\"csrf_token\"")?;
+    let start = html[..at].rfind('<')?;
+    let end = at + html[at..].find('>')?;
+    Some(decode_entities(attribute(&html[start..end], "value")?))
+}
+
+/// `(id, name)` of the options of the first language `<select>`.
+fn parse_languages(html: &str) -> Vec<(String, String)> {
+    let Some(at) = html.find("name=\"data.LanguageId\"") else {
+        return Vec::new();
+    };
+    let rest = &html[at..];
+    let select = &rest[..rest.find("</select>").unwrap_or(rest.len())];
+    select
+        .split("<option")
+        .skip(1)
+        .filter_map(|option| {
+            let id = attribute(option, "value")?;
+            let name = text(option.split_once('>')?.1);
+            (!id.is_empty()).then(|| (id.to_string(), name))
+        })
+        .collect()
+}
+
+/// Text of the `<title>` element.
+fn page_title(html: &str) -> Option<String> {
+    Some(text(element(html, "title")?))
+}
+
+/// Rows of the task table on `/contests/<id>/tasks`.
+pub fn parse_task_list(html: &str, base_url: &str) -> Vec<TaskInfo> {
+    let Some(body) = element(html, "tbody") else {
+        return Vec::new();
+    };
+    let mut tasks = Vec::new();
+    for row in body.split("<tr").skip(1) {
+        // Contents of each cell; everything up to the next cell is fine since tags are dropped.
+        let cells: Vec<&str> = row
+            .split("<td")
+            .skip(1)
+            .map(|cell| cell.split_once('>').map_or("", |(_, rest)| rest))
+            .collect();
+        if cells.len() < 2 {
+            continue;
+        }
+        let Some(href) = attribute(cells[0], "href") else {
+            continue;
+        };
+        let url = if href.starts_with('/') {
+            format!("{base_url}{href}")
+        } else {
+            href.to_string()
+        };
+        tasks.push(TaskInfo {
+            id: href.rsplit('/').next().unwrap_or_default().to_string(),
+            label: text(cells[0]),
+            title: text(cells[1]),
+            url,
+            time_limit: cells.get(2).and_then(|c| parse_duration(&text(c)).ok()),
+            memory_limit: cells.get(3).and_then(|c| parse_memory(&text(c)).ok()),
+        });
+    }
+    tasks
+}
+
+/// Sample cases of a task page, numbered as on the page.
+///
+/// Pages carry the statement in Japanese and English; both list the same samples, so the
+/// first occurrence of each number is kept.
+pub fn parse_samples(html: &str) -> Vec<Sample> {
+    let mut inputs = BTreeMap::new();
+    let mut outputs = BTreeMap::new();
+    for section in html.split("<h3").skip(1) {
+        let Some((heading, body)) = section.split_once("</h3>") else {
+            continue;
+        };
+        let heading = text(heading.split_once('>').map_or("", |(_, h)| h));
+        let Some((is_input, number)) = sample_heading(&heading) else {
+            continue;
+        };
+        let Some(pre) = element(body, "pre") else {
+            continue;
+        };
+        let content = pre_text(pre);
+        let map = if is_input { &mut inputs } else { &mut outputs };
+        map.entry(number).or_insert(content);
+    }
+    inputs
+        .into_iter()
+        .filter_map(|(number, input)| {
+            let output = outputs.remove(&number)?;
+            Some(Sample { input, output })
+        })
+        .collect()
+}
+
+/// Points of a task, from "配点 : 100 点" or "Score : 100 points" on its page.
+fn parse_score(html: &str) -> Option<u64> {
+    ["配点", "Score"].iter().find_map(|marker| {
+        html.match_indices(marker).find_map(|(start, _)| {
+            let rest = &html[start + marker.len()..];
+            let line = text(&rest[..rest.find("</p>")?]);
+            line.trim_start_matches([' ', ':'])
+                .split_whitespace()
+                .next()?
+                .parse()
+                .ok()
+        })
+    })
+}
+
+/// Whether `heading` introduces a sample input (`true`) or output, and its number.
+fn sample_heading(heading: &str) -> Option<(bool, u32)> {
+    let heading = heading.trim();
+    let (is_input, number) = if let Some(n) = heading.strip_prefix("入力例") {
+        (true, n)
+    } else if let Some(n) = heading.strip_prefix("出力例") {
+        (false, n)
+    } else if let Some(n) = heading.strip_prefix("Sample Input") {
+        (true, n)
+    } else if let Some(n) = heading.strip_prefix("Sample Output") {
+        (false, n)
+    } else {
+        return None;
+    };
+    // The heading goes on with the label of its copy button.
+    Some((is_input, number.split_whitespace().next()?.parse().ok()?))
+}
+
+/// Contents of the first `<tag ...>...</tag>` in `html`.
+fn element<'a>(html: &'a str, tag: &str) -> Option<&'a str> {
+    let open = html.find(&format!("<{tag}"))?;
+    let rest = &html[open..];
+    let start = rest.find('>')? + 1;
+    let end = rest.find(&format!("</{tag}>"))?;
+    rest.get(start..end)
+}
+
+/// Value of the first `name="..."` attribute in `html`.
+fn attribute<'a>(html: &'a str, name: &str) -> Option<&'a str> {
+    let start = html.find(&format!("{name}=\""))? + name.len() + 2;
+    let len = html[start..].find('"')?;
+    Some(&html[start..start + len])
+}
+
+/// Text content of a fragment, with whitespace collapsed.
+fn text(html: &str) -> String {
+    decode_entities(&strip_tags(html))
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Text content of a `<pre>` block as a test case file: `\n` line ends, ending with one.
+fn pre_text(html: &str) -> String {
+    let content = decode_entities(&strip_tags(html)).replace("\r\n", "\n");
+    // A newline right after `<pre>` is not part of the content.
+    let mut content = content.strip_prefix('\n').unwrap_or(&content).to_string();
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content
+}
+
+fn strip_tags(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => out.push(c),
+            _ => {}
+        }
+    }
+    out
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest
+            .find(';')
+            .filter(|&end| end <= 10)
+            .and_then(|end| Some((decode_entity(&rest[1..end])?, end)));
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn decode_entity(name: &str) -> Option<char> {
+    match name {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some(' '),
+        _ => {
+            let code = name.strip_prefix('#')?;
+            let code = match code.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => code.parse().ok()?,
+            };
+            char::from_u32(code)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+    };
+
+    const TASK_LIST: &str = include_str!("../tests/fixtures/atcoder/tasks.html");
+    const TASK_PAGE: &str = include_str!("../tests/fixtures/atcoder/task.html");
+
+    /// Serve `pages` (request path → body) on a local port and return its base URL, the way
+    /// a fixture server is used with `--base-url`.
+    fn serve(pages: Vec<(String, String)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request = String::new();
+                reader.read_line(&mut request).unwrap();
+                let mut header = String::new();
+                while reader.read_line(&mut header).unwrap() > 2 {
+                    header.clear();
+                }
+                let path = request.split_whitespace().nth(1).unwrap_or_default();
+                let (status, body) = match pages.iter().find(|(p, _)| p == path) {
+                    Some((_, body)) => ("200 OK", body.as_str()),
+                    None => ("404 Not Found", ""),
+                };
+                let response = format!(
+                    "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        base_url
+    }
+
+    #[test]
+    fn task_list() {
+        let tasks = parse_task_list(TASK_LIST, "http://localhost:8000");
+        let labels: Vec<&str> = tasks.iter().map(|t| t.label.as_str()).collect();
+        assert_eq!(labels, ["A", "B", "Ex"]);
+        assert_eq!(tasks[0].id, "abc999_a");
+        assert_eq!(tasks[0].title, "Sum & Product");
+        assert_eq!(
+            tasks[0].url,
+            "http://localhost:8000/contests/abc999/tasks/abc999_a"
+        );
+        assert_eq!(tasks[0].time_limit, Some(Duration::from_secs(2)));
+        assert_eq!(tasks[0].memory_limit, Some(1024 << 20));
+        assert_eq!(tasks[1].title, "Less < Than");
+        assert_eq!(tasks[1].time_limit, Some(Duration::from_millis(3500)));
+        assert_eq!(tasks[1].memory_limit, Some(256 << 20));
+        assert_eq!(tasks[2].id, "abc999_ex");
+        assert_eq!(tasks[2].title, "Quotes \"and\" 'apostrophes'");
+        assert_eq!(
+            page_title(TASK_LIST).unwrap(),
+            "Tasks - AtCoder Beginner Contest 999"
+        );
+    }
+
+    #[test]
+    fn task_list_without_table() {
+        assert!(parse_task_list("<html><body>not started</body></html>", "").is_empty());
+    }
+
+    #[test]
+    fn samples() {
+        let samples = parse_samples(TASK_PAGE);
+        assert_eq!(samples.len(), 3);
+        assert_eq!(samples[0].input, "1 2\n");
+        assert_eq!(samples[0].output, "3\n");
+        // The newline right after `<pre>` is dropped, entities are decoded, and the
+        // missing final newline is added.
+        assert_eq!(samples[1].input, "10 <20> &\n");
+        assert_eq!(samples[1].output, "30\n-5\n");
+        assert_eq!(samples[2].input, "1\n2\n");
+    }
+
+    #[test]
+    fn samples_are_taken_once_from_the_japanese_statement() {
+        // The English statement repeats every sample; the first occurrence wins.
+        let samples = parse_samples(TASK_PAGE);
+        assert_eq!(samples[2].output, "はい\n");
+        let english = &TASK_PAGE[TASK_PAGE.find("lang-en").unwrap()..];
+        let samples = parse_samples(english);
+        assert_eq!(samples.len(), 3);
+        assert_eq!(samples[2].output, "Yes\n");
+    }
+
+    #[test]
+    fn sample_with_crlf() {
+        let html = "<h3>入力例 1</h3><pre>\r\n1\r\n2\r\n</pre><h3>出力例 1</h3><pre>3</pre>";
+        let samples = parse_samples(html);
+        assert_eq!(samples[0].input, "1\n2\n");
+        assert_eq!(samples[0].output, "3\n");
+    }
+
+    #[test]
+    fn score() {
+        assert_eq!(parse_score(TASK_PAGE), Some(100));
+        let english = &TASK_PAGE[TASK_PAGE.find("lang-en").unwrap()..];
+        assert_eq!(parse_score(english), Some(100));
+        assert_eq!(parse_score("<p>no score here</p>"), None);
+    }
+
+    #[test]
+    fn entities() {
+        assert_eq!(
+            decode_entities("a &lt;b&gt; &amp;&amp; &quot;c&quot;"),
+            "a <b> && \"c\""
+        );
+        assert_eq!(decode_entities("&#43;&#x2B;&#X2b;&#10;"), "+++\n");
+        assert_eq!(decode_entities("&#12354;&nbsp;&apos;"), "あ '");
+        // Anything that is not a known entity is left as is.
+        assert_eq!(
+            decode_entities("A & B &unknown; &#xZZ; &"),
+            "A & B &unknown; &#xZZ; &"
+        );
+        assert_eq!(decode_entities("&amp;lt;"), "&lt;");
+    }
+
+    #[test]
+    fn csrf_token_and_languages() {
+        let html = r#"<form><input type="hidden" name="csrf_token" value="tok&#43;en/=="/>
+            <select class="form-control" name="data.LanguageId"><option></option>
+            <option value="5001">C++ 20 (gcc 12.2)</option>
+            <option value="5054" data-mime="text/x-rustsrc">Rust (rustc 1.70.0)</option>
+            </select></form>"#;
+        assert_eq!(parse_csrf_token(html).unwrap(), "tok+en/==");
+        assert_eq!(
+            parse_languages(html),
+            [
+                ("5001".to_string(), "C++ 20 (gcc 12.2)".to_string()),
+                ("5054".to_string(), "Rust (rustc 1.70.0)".to_string()),
+            ]
+        );
+    }
+
+    /// Status responses recorded while a submission was judged.
+    const STATUS_WAITING: &str = include_str!("../tests/fixtures/atcoder/status-waiting.json");
+    const STATUS_JUDGING: &str = include_str!("../tests/fixtures/atcoder/status-judging.json");
+    const STATUS_FINAL: &str = include_str!("../tests/fixtures/atcoder/status-final.json");
+
+    #[test]
+    fn submission_waiting() {
+        let status = parse_submission_status(STATUS_WAITING, 48213).unwrap();
+        assert_eq!(status.status, "WJ");
+        assert!(!status.is_final());
+        assert_eq!((status.time, status.memory), (None, None));
+        assert_eq!(status.interval, Some(Duration::from_millis(300)));
+    }
+
+    #[test]
+    fn submission_being_judged() {
+        let status = parse_submission_status(STATUS_JUDGING, 48213).unwrap();
+        assert_eq!(status.status, "3/20 AC");
+        assert!(!status.is_final());
+        assert_eq!((status.time, status.memory), (None, None));
+        assert_eq!(status.interval, Some(Duration::from_millis(1100)));
+    }
+
+    #[test]
+    fn submission_judged() {
+        let status = parse_submission_status(STATUS_FINAL, 48213).unwrap();
+        assert_eq!(status.status, "AC");
+        assert!(status.is_final());
+        assert_eq!(status.time.as_deref(), Some("23 ms"));
+        assert_eq!(status.memory.as_deref(), Some("3800 KB"));
+        assert_eq!(status.score.as_deref(), Some("100"));
+        assert_eq!(status.interval, None);
+    }
+
+    #[test]
+    fn submission_missing_from_the_status() {
+        assert!(parse_submission_status(STATUS_FINAL, 1).is_err());
+        assert!(parse_submission_status("<html>login</html>", 48213).is_err());
+    }
+
+    #[test]
+    fn final_verdicts() {
+        let verdict = |status: &str| SubmissionStatus {
+            status: status.to_string(),
+            time: None,
+            memory: None,
+            score: None,
+            interval: None,
+        };
+        for status in ["AC", "WA", "TLE", "MLE", "RE", "CE", "OLE", "IE", "QLE"] {
+            assert!(verdict(status).is_final(), "{status}");
+        }
+        for status in ["WJ", "WR", "Judging", "1/3 AC", "0/20 WA", ""] {
+            assert!(!verdict(status).is_final(), "{status}");
+        }
+    }
+
+    #[test]
+    fn submission_status_from_a_stand_in() {
+        let path = "/contests/abc999/submissions/me/status/json?reload=true&sids%5B%5D=48213";
+        let base_url = serve(vec![(path.to_string(), STATUS_JUDGING.to_string())]);
+        let status = Client::new(&base_url)
+            .submission_status("abc999", 48213)
+            .unwrap();
+        assert_eq!(status.status, "3/20 AC");
+    }
+
+    #[test]
+    fn contest_from_a_fixture_server() {
+        let base_url = serve(vec![
+            ("/contests/abc999/tasks".to_string(), TASK_LIST.to_string()),
+            (
+                "/contests/abc999/tasks/abc999_a".to_string(),
+                TASK_PAGE.to_string(),
+            ),
+        ]);
+        let client = Client::new(&base_url);
+        let (title, tasks) = client.contest("abc999").unwrap();
+        assert_eq!(title, "AtCoder Beginner Contest 999");
+        assert_eq!(tasks.len(), 3);
+        assert_eq!(
+            tasks[0].url,
+            format!("{base_url}/contests/abc999/tasks/abc999_a")
+        );
+        let page = client.task_page(&tasks[0]).unwrap();
+        assert_eq!(page.samples.len(), 3);
+        assert_eq!(page.score, Some(100));
+        // A missing page is an error, not an empty task.
+        assert!(client.task_page(&tasks[1]).is_err());
+        assert!(client.contest("abc000").is_err());
+    }
+}
@@ -0,0 +1,1022 @@
+//! Bundling a solution and the local libraries it uses into one submittable file.
+//!
+//! Libraries are the `path` dependencies of the contest's `Cargo.toml` (and theirs, in
+//! turn). Each used library is inlined as a module named after the crate, so that
+//! `use mylib::x` in the solution keeps working; inside it `crate::` paths are rewritten
+//! to `crate::mylib::`. Only the items reachable from the solution are kept, and
+//! `#[cfg(test)]` items and doc comments are dropped.
+//!
+//! Reachability goes by name rather than by resolving paths: an item is kept when its name
+//! is mentioned by the solution or by an item already kept. That may keep an unused item
+//! sharing a name with a used one, which is harmless.
+
+use anyhow::{bail, Context, Result};
+use proc_macro2::{Delimiter, Group, Ident, Punct, Spacing, Span, TokenStream, TokenTree};
+use quote::ToTokens;
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+use syn::{
+    parse_quote, AttrStyle, Attribute, ImplItem, Item, ItemMacro, ItemMod, TraitItem, UseTree,
+};
+use toml_edit::{DocumentMut, Item as TomlItem, Table};
+
+/// A local library crate.
+struct Library {
+    /// Items of the crate root, with the files of its modules already inlined.
+    items: Vec<Item>,
+    /// Inner attributes of the crate root worth keeping (`allow`s).
+    attrs: Vec<Attribute>,
+}
+
+/// What went into a bundle.
+pub struct Bundle {
+    pub source: String,
+    /// Used libraries with the number of items kept and available.
+    pub libraries: Vec<(String, usize, usize)>,
+}
+
+/// Bundle `solution`, a source of the contest in `dir`, with the libraries it uses.
+pub fn bundle(dir: &Path, solution: &str) -> Result<Bundle> {
+    let libraries = load_libraries(dir)?;
+    let solution_tokens: TokenStream = solution
+        .parse()
+        .map_err(|e| anyhow::anyhow!("the solution does not parse: {e}"))?;
+
+    let (defined, names) = reachable_names(&libraries, &solution_tokens);
+
+    let mut source = strip_extern_crates(solution, &libraries);
+    let mut summary = Vec::new();
+    let mut bundled = Vec::new();
+    for (name, library) in &libraries {
+        if !names.contains(name) {
+            continue;
+        }
+        let items = prune(&library.items, &defined, &names);
+        summary.push((
+            name.clone(),
+            count_items(&items),
+            count_items(&library.items),
+        ));
+        let content = rewrite(
+            quote::quote!(#(#items)*),
+            name,
+            &libraries.keys().map(String::as_str).collect::<Vec<_>>(),
+        );
+        let items: Vec<Item> = syn::parse2::<syn::File>(content)
+            .with_context(|| format!("Failed to re-read library {name} after rewriting"))?
+            .items;
+        let ident = Ident::new(name, Span::call_site());
+        let attrs = &library.attrs;
+        bundled.push(Item::Mod(parse_quote! {
+            #[allow(dead_code, unused_imports, unused_macros)]
+            #(#attrs)*
+            mod #ident {
+                #(#items)*
+            }
+        }));
+    }
+    if !bundled.is_empty() {
+        let file = syn::File {
+            shebang: None,
+            attrs: Vec::new(),
+            items: bundled,
+        };
+        if !source.ends_with('\n') {
+            source.push('\n');
+        }
+        source.push_str("\n// ---- bundled libraries ----\n\n");
+        source.push_str(&prettyplease::unparse(&file));
+    }
+    Ok(Bundle {
+        source,
+        libraries: summary,
+    })
+}
+
+/// Check that `source` compiles on its own, with registry dependencies only.
+///
+/// The check runs in a scratch package under `target/kp/bundle`, sharing the contest's
+/// target directory so that dependencies are not built twice.
+pub fn verify(dir: &Path, problem: &str, source: &str) -> Result<()> {
+    let package_dir = dir.join("target").join("kp").join("bundle").join(problem);
+    fs::create_dir_all(package_dir.join("src"))?;
+    let doc = scratch_manifest(dir, problem)?;
+    fs::write(package_dir.join("Cargo.toml"), doc.to_string())?;
+    fs::write(package_dir.join("src").join("main.rs"), source)?;
+
+    let target_dir = fs::canonicalize(dir)?.join("target");
+    let output = Command::new("cargo")
+        .current_dir(&package_dir)
+        .args(["check", "--quiet", "--message-format=short"])
+        .env("CARGO_TARGET_DIR", target_dir)
+        .output()
+        .context("Failed to spawn cargo check")?;
+    if !output.status.success() {
+        eprint!("{}", String::from_utf8_lossy(&output.stderr));
+        bail!(
+            "the bundled source does not compile on its own (see {})",
+            package_dir.join("src").join("main.rs").display()
+        );
+    }
+    Ok(())
+}
+
+/// Manifest of the scratch package checking the bundle of `problem`.
+///
+/// Its dependencies are the registry (and git) dependencies of the contest and of every
+/// library it bundles, as the inlined library code still needs those; where several
+/// manifests name the same dependency, the contest's comes first.
+fn scratch_manifest(dir: &Path, problem: &str) -> Result<DocumentMut> {
+    let manifest = fs::read_to_string(dir.join("Cargo.toml"))?.parse::<DocumentMut>()?;
+    let mut doc = DocumentMut::new();
+    let mut package = Table::new();
+    package["name"] = toml_edit::value(format!("bundle-{problem}"));
+    package["version"] = toml_edit::value("0.0.0");
+    let edition = manifest["package"]["edition"].as_str().unwrap_or("2021");
+    package["edition"] = toml_edit::value(edition);
+    doc["package"] = TomlItem::Table(package);
+
+    let mut dependencies = Table::new();
+    let mut seen = BTreeSet::new();
+    let mut pending = VecDeque::from([dir.to_path_buf()]);
+    while let Some(dir) = pending.pop_front() {
+        let manifest_path = dir.join("Cargo.toml");
+        let manifest = fs::read_to_string(&manifest_path)
+            .with_context(|| format!("Failed to read {}", manifest_path.display()))?
+            .parse::<DocumentMut>()?;
+        let Some(table) = manifest
+            .get("dependencies")
+            .and_then(TomlItem::as_table_like)
+        else {
+            continue;
+        };
+        for (name, spec) in table.iter() {
+            match spec.get("path").and_then(TomlItem::as_str) {
+                Some(path) => {
+                    if seen.insert(name.replace('-', "_")) {
+                        pending.push_back(dir.join(path));
+                    }
+                }
+                None => {
+                    if !dependencies.contains_key(name) {
+                        dependencies.insert(name, spec.clone());
+                    }
+                }
+            }
+        }
+    }
+    doc["dependencies"] = TomlItem::Table(dependencies);
+    // Keep cargo from looking for a workspace in the directories above.
+    doc["workspace"] = TomlItem::Table(Table::new());
+    Ok(doc)
+}
+
+/// The path dependencies of the contest, and theirs, by crate name.
+fn load_libraries(dir: &Path) -> Result<BTreeMap<String, Library>> {
+    let mut libraries = BTreeMap::new();
+    let mut pending = path_dependencies(dir)?;
+    while let Some((name, path)) = pending.pop() {
+        if libraries.contains_key(&name) {
+            continue;
+        }
+        pending.extend(path_dependencies(&path)?);
+        let library = load_library(&path)
+            .with_context(|| format!("Failed to load library {} at {}", name, path.display()))?;
+        libraries.insert(name, library);
+    }
+    Ok(libraries)
+}
+
+/// `(crate name, directory)` of each `path` dependency in the manifest of `dir`.
+pub fn path_dependencies(dir: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let manifest_path = dir.join("Cargo.toml");
+    let manifest = fs::read_to_string(&manifest_path)
+        .with_context(|| format!("Failed to read {}", manifest_path.display()))?
+        .parse::<DocumentMut>()?;
+    let Some(table) = manifest
+        .get("dependencies")
+        .and_then(TomlItem::as_table_like)
+    else {
+        return Ok(Vec::new());
+    };
+    Ok(table
+        .iter()
+        .filter_map(|(name, spec)| {
+            let path = spec.get("path")?.as_str()?;
+            Some((name.replace('-', "_"), dir.join(path)))
+        })
+        .collect())
+}
+
+fn load_library(dir: &Path) -> Result<Library> {
+    let manifest = fs::read_to_string(dir.join("Cargo.toml"))?.parse::<DocumentMut>()?;
+    let lib_path = manifest
+        .get("lib")
+        .and_then(|lib| lib.get("path"))
+        .and_then(TomlItem::as_str)
+        .unwrap_or("src/lib.rs");
+    let lib_path = dir.join(lib_path);
+    let mut file = parse_file(&lib_path)?;
+    let module_dir = lib_path.parent().unwrap_or(dir).to_path_buf();
+    strip_tests(&mut file.items);
+    load_modules(&mut file.items, &module_dir)?;
+    Ok(Library::new(file))
+}
+
+impl Library {
+    /// A library of a crate root with its module files already inlined.
+    fn new(mut file: syn::File) -> Library {
+        let mut reexports = Vec::new();
+        unexport_macros(&mut file.items, &mut Vec::new(), &mut reexports);
+        file.items.extend(reexports);
+
+        Library {
+            items: file.items,
+            attrs: file
+                .attrs
+                .into_iter()
+                .filter(|attr| attr.path().is_ident("allow"))
+                .map(|mut attr| {
+                    attr.style = AttrStyle::Outer;
+                    attr
+                })
+                .collect(),
+        }
+    }
+}
+
+fn parse_file(path: &Path) -> Result<syn::File> {
+    let source =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    syn::parse_file(&source).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Replace `mod x;` declarations with the contents of their files, found under `dir`.
+fn load_modules(items: &mut [Item], dir: &Path) -> Result<()> {
+    for item in items {
+        let Item::Mod(module) = item else {
+            continue;
+        };
+        let name = module.ident.to_string();
+        let child_dir = dir.join(&name);
+        if module.content.is_none() {
+            let path = [dir.join(format!("{name}.rs")), child_dir.join("mod.rs")]
+                .into_iter()
+                .find(|path| path.is_file())
+                .with_context(|| format!("no file for module {} in {}", name, dir.display()))?;
+            let mut file = parse_file(&path)?;
+            strip_tests(&mut file.items);
+            module.content = Some((Default::default(), file.items));
+            module.semi = None;
+        }
+        if let Some((_, items)) = &mut module.content {
+            load_modules(items, &child_dir)?;
+        }
+    }
+    Ok(())
+}
+
+/// Turn `#[macro_export]` macros into ones re-exported with `pub(crate) use`.
+///
+/// An exported macro would land at the root of the bundle, not in the library's module,
+/// and clash with the solution's `use mylib::the_macro;`. Macros of nested modules are
+/// also re-exported from the library root (in `root`), where `#[macro_export]` put them.
+fn unexport_macros(items: &mut Vec<Item>, path: &mut Vec<Ident>, root: &mut Vec<Item>) {
+    let mut i = 0;
+    while i < items.len() {
+        match &mut items[i] {
+            Item::Macro(mac) if mac.attrs.iter().any(|a| a.path().is_ident("macro_export")) => {
+                mac.attrs.retain(|a| !a.path().is_ident("macro_export"));
+                if let Some(name) = mac.ident.clone() {
+                    items.insert(i + 1, parse_quote!(pub(crate) use #name;));
+                    if !path.is_empty() {
+                        root.push(parse_quote!(pub(crate) use crate::#(#path::)*#name;));
+                    }
+                    i += 1;
+                }
+            }
+            Item::Mod(ItemMod {
+                ident,
+                content: Some((_, items)),
+                ..
+            }) => {
+                path.push(ident.clone());
+                unexport_macros(items, path, root);
+                path.pop();
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+}
+
+/// Drop `#[cfg(test)]` items, down to those inside modules, impls and traits.
+fn strip_tests(items: &mut Vec<Item>) {
+    items.retain(|item| {
+        let attrs = match item {
+            Item::Fn(f) => &f.attrs,
+            Item::Mod(m) => &m.attrs,
+            Item::Impl(i) => &i.attrs,
+            Item::Use(u) => &u.attrs,
+            Item::Struct(s) => &s.attrs,
+            Item::Enum(e) => &e.attrs,
+            Item::Const(c) => &c.attrs,
+            Item::Static(s) => &s.attrs,
+            Item::Trait(t) => &t.attrs,
+            Item::Macro(m) => &m.attrs,
+            _ => return true,
+        };
+        !is_test_only(attrs)
+    });
+    for item in items {
+        match item {
+            Item::Mod(ItemMod {
+                content: Some((_, items)),
+                ..
+            }) => strip_tests(items),
+            Item::Impl(imp) => imp.items.retain(|item| match item {
+                ImplItem::Fn(f) => !is_test_only(&f.attrs),
+                ImplItem::Const(c) => !is_test_only(&c.attrs),
+                _ => true,
+            }),
+            Item::Trait(trait_) => trait_.items.retain(|item| match item {
+                TraitItem::Fn(f) => !is_test_only(&f.attrs),
+                _ => true,
+            }),
+            _ => {}
+        }
+    }
+}
+
+fn is_test_only(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        attr.path().is_ident("cfg")
+            && attr
+                .meta
+                .require_list()
+                .is_ok_and(|list| list.tokens.to_string() == "test")
+    })
+}
+
+/// The names of all library items and libraries, and those reachable from `solution`.
+fn reachable_names(
+    libraries: &BTreeMap<String, Library>,
+    solution: &TokenStream,
+) -> (BTreeSet<String>, BTreeSet<String>) {
+    let mut defined: BTreeSet<String> = libraries.keys().cloned().collect();
+    for library in libraries.values() {
+        defined_names(&library.items, &mut defined);
+    }
+    // Start from the names the solution mentions and add those of the items they keep,
+    // until nothing new shows up.
+    let mut names = BTreeSet::new();
+    collect_idents(solution, &mut names);
+    loop {
+        let known = names.len();
+        for (name, library) in libraries {
+            if names.contains(name) {
+                reach(&library.items, &defined, &mut names);
+            }
+        }
+        if names.len() == known {
+            return (defined, names);
+        }
+    }
+}
+
+/// Add the names of the items in `items`, down into modules, to `defined`.
+fn defined_names(items: &[Item], defined: &mut BTreeSet<String>) {
+    for item in items {
+        let ident = match item {
+            Item::Fn(item) => &item.sig.ident,
+            Item::Struct(item) => &item.ident,
+            Item::Enum(item) => &item.ident,
+            Item::Union(item) => &item.ident,
+            Item::Trait(item) => &item.ident,
+            Item::TraitAlias(item) => &item.ident,
+            Item::Type(item) => &item.ident,
+            Item::Const(item) => &item.ident,
+            Item::Static(item) => &item.ident,
+            Item::Macro(ItemMacro {
+                ident: Some(ident), ..
+            }) => ident,
+            Item::Mod(item) => {
+                if let Some((_, items)) = &item.content {
+                    defined_names(items, defined);
+                }
+                &item.ident
+            }
+            _ => continue,
+        };
+        defined.insert(ident.to_string());
+    }
+}
+
+/// Whether `item` is needed, now that `names` are mentioned by the solution and the items
+/// kept so far. `defined` holds the names of all library items.
+///
+/// A named item is needed once its name is mentioned. Impls have no name and are needed
+/// as long as the library types and traits they are for are; `use`s are needed when a
+/// name they import is, and always when they import from outside the libraries.
+fn is_reachable(item: &Item, defined: &BTreeSet<String>, names: &BTreeSet<String>) -> bool {
+    let named = |ident: &Ident| names.contains(&ident.to_string());
+    match item {
+        Item::Fn(item) => named(&item.sig.ident),
+        Item::Struct(item) => named(&item.ident),
+        Item::Enum(item) => named(&item.ident),
+        Item::Union(item) => named(&item.ident),
+        Item::TraitAlias(item) => named(&item.ident),
+        Item::Type(item) => named(&item.ident),
+        Item::Const(item) => named(&item.ident),
+        Item::Static(item) => named(&item.ident),
+        Item::Mod(item) => named(&item.ident),
+        Item::Macro(ItemMacro {
+            ident: Some(ident), ..
+        }) => named(ident),
+        // A method call does not name the trait, so any of its items count.
+        Item::Trait(item) => {
+            named(&item.ident)
+                || item.items.iter().any(|item| match item {
+                    TraitItem::Fn(item) => named(&item.sig.ident),
+                    TraitItem::Const(item) => named(&item.ident),
+                    TraitItem::Type(item) => named(&item.ident),
+                    _ => false,
+                })
+        }
+        Item::Impl(item) => {
+            let mut header = BTreeSet::new();
+            collect_idents(&item.self_ty.to_token_stream(), &mut header);
+            if let Some((_, path, _)) = &item.trait_ {
+                collect_idents(&path.to_token_stream(), &mut header);
+            }
+            header
+                .iter()
+                .filter(|name| defined.contains(*name))
+                .all(|name| names.contains(name))
+        }
+        Item::Use(item) => imports_reachable(&item.tree, true, defined, names),
+        // Macro calls, `extern crate` and the like.
+        _ => true,
+    }
+}
+
+/// Whether the `use` tree `tree` imports something needed; `root` tells whether it starts
+/// the path.
+fn imports_reachable(
+    tree: &UseTree,
+    root: bool,
+    defined: &BTreeSet<String>,
+    names: &BTreeSet<String>,
+) -> bool {
+    match tree {
+        UseTree::Path(path) => {
+            let ident = path.ident.to_string();
+            let local =
+                ["crate", "self", "super"].contains(&ident.as_str()) || defined.contains(&ident);
+            (root && !local) || imports_reachable(&path.tree, false, defined, names)
+        }
+        UseTree::Name(name) => {
+            let ident = name.ident.to_string();
+            (root && !defined.contains(&ident)) || ident == "self" || names.contains(&ident)
+        }
+        // `as _` imports a trait for its methods.
+        UseTree::Rename(rename) => {
+            rename.rename == "_" || names.contains(&rename.rename.to_string())
+        }
+        UseTree::Glob(_) => true,
+        UseTree::Group(group) => group
+            .items
+            .iter()
+            .any(|tree| imports_reachable(tree, root, defined, names)),
+    }
+}
+
+/// Add the names mentioned by the needed ones of `items`, down into modules, to `names`.
+fn reach(items: &[Item], defined: &BTreeSet<String>, names: &mut BTreeSet<String>) {
+    for item in items {
+        if !is_reachable(item, defined, names) {
+            continue;
+        }
+        match item {
+            Item::Mod(ItemMod {
+                content: Some((_, items)),
+                ..
+            }) => reach(items, defined, names),
+            item => collect_idents(&item.to_token_stream(), names),
+        }
+    }
+}
+
+/// The needed ones of `items`, down into modules.
+fn prune(items: &[Item], defined: &BTreeSet<String>, names: &BTreeSet<String>) -> Vec<Item> {
+    items
+        .iter()
+        .filter(|item| is_reachable(item, defined, names))
+        .map(|item| match item {
+            Item::Mod(module) => {
+                let mut module = module.clone();
+                if let Some((_, items)) = &mut module.content {
+                    *items = prune(items, defined, names);
+                }
+                Item::Mod(module)
+            }
+            item => item.clone(),
+        })
+        .collect()
+}
+
+/// Number of items in `items`, counting those inside modules rather than the modules.
+fn count_items(items: &[Item]) -> usize {
+    items
+        .iter()
+        .map(|item| match item {
+            Item::Mod(ItemMod {
+                content: Some((_, items)),
+                ..
+            }) => count_items(items),
+            _ => 1,
+        })
+        .sum()
+}
+
+fn collect_idents(tokens: &TokenStream, out: &mut BTreeSet<String>) {
+    for token in tokens.clone() {
+        match token {
+            TokenTree::Ident(ident) => {
+                out.insert(ident.to_string());
+            }
+            TokenTree::Group(group) => collect_idents(&group.stream(), out),
+            _ => {}
+        }
+    }
+}
+
+/// Whether `tokens[i..]` starts with `::`.
+fn is_path_separator(tokens: &[TokenTree], i: usize) -> bool {
+    matches!(
+        (tokens.get(i), tokens.get(i + 1)),
+        (Some(TokenTree::Punct(a)), Some(TokenTree::Punct(b)))
+            if a.as_char() == ':' && a.spacing() == Spacing::Joint && b.as_char() == ':'
+    )
+}
+
+/// Whether `tokens[i]` comes right after `::`, i.e. is not the start of a path.
+fn follows_path_separator(tokens: &[TokenTree], i: usize) -> bool {
+    i >= 2 && is_path_separator(tokens, i - 2)
+}
+
+/// Make the code of library `name` work as a module at the root of the bundle:
+/// `crate::` becomes `crate::name::`, paths into other bundled libraries become
+/// `crate::other::`, and doc comments go.
+fn rewrite(tokens: TokenStream, name: &str, libraries: &[&str]) -> TokenStream {
+    let tokens: Vec<TokenTree> = tokens.into_iter().collect();
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        i += 1;
+        match token {
+            TokenTree::Punct(p) if p.as_char() == '#' => {
+                // `#[doc = ...]` and `#![doc = ...]`
+                let bang =
+                    matches!(&tokens.get(i), Some(TokenTree::Punct(p)) if p.as_char() == '!');
+                let attr = tokens.get(i + usize::from(bang));
+                if let Some(TokenTree::Group(group)) = attr {
+                    let first = group.stream().into_iter().next();
+                    if group.delimiter() == Delimiter::Bracket
+                        && matches!(first, Some(TokenTree::Ident(ident)) if ident == "doc")
+                    {
+                        i += 1 + usize::from(bang);
+                        continue;
+                    }
+                }
+                out.push(token.clone());
+            }
+            TokenTree::Group(group) => {
+                let mut rewritten =
+                    Group::new(group.delimiter(), rewrite(group.stream(), name, libraries));
+                rewritten.set_span(group.span());
+                out.push(TokenTree::Group(rewritten));
+            }
+            TokenTree::Ident(ident) if ident == "crate" && is_path_separator(&tokens, i) => {
+                out.push(token.clone());
+                push_path_segment(&mut out, name);
+            }
+            TokenTree::Ident(ident)
+                if libraries.iter().any(|lib| ident == lib)
+                    && is_path_separator(&tokens, i)
+                    && !follows_path_separator(&tokens, i - 1) =>
+            {
+                out.push(TokenTree::Ident(Ident::new("crate", ident.span())));
+                push_path_segment(&mut out, &ident.to_string());
+            }
+            _ => out.push(token.clone()),
+        }
+    }
+    out.into_iter().collect()
+}
+
+/// Append `::segment`.
+fn push_path_segment(out: &mut Vec<TokenTree>, segment: &str) {
+    out.push(TokenTree::Punct(Punct::new(':', Spacing::Joint)));
+    out.push(TokenTree::Punct(Punct::new(':', Spacing::Alone)));
+    out.push(TokenTree::Ident(Ident::new(segment, Span::call_site())));
+}
+
+/// Drop `extern crate lib;` lines for bundled libraries from the solution.
+fn strip_extern_crates(solution: &str, libraries: &BTreeMap<String, Library>) -> String {
+    solution
+        .lines()
+        .filter(|line| {
+            let line = line.trim();
+            !libraries
+                .keys()
+                .any(|name| line == format!("extern crate {name};"))
+        })
+        .map(|line| format!("{line}\n"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn library(source: &str) -> Library {
+        let mut file = syn::parse_file(source).unwrap();
+        strip_tests(&mut file.items);
+        Library::new(file)
+    }
+
+    /// The items of the library `name` kept for `solution`, as tokens.
+    fn kept(libraries: &[(&str, &str)], name: &str, solution: &str) -> String {
+        let libraries: BTreeMap<String, Library> = libraries
+            .iter()
+            .map(|(name, source)| (name.to_string(), library(source)))
+            .collect();
+        let (defined, names) = reachable_names(&libraries, &solution.parse().unwrap());
+        if !names.contains(name) {
+            return "(unused)".to_string();
+        }
+        let items = prune(&libraries[name].items, &defined, &names);
+        tokens(&quote::quote!(#(#items)*).to_string())
+    }
+
+    /// `source` formatted, so that sources differing in spacing only compare equal.
+    fn tokens(source: &str) -> String {
+        prettyplease::unparse(&syn::parse_file(source).unwrap())
+    }
+
+    #[test]
+    fn keeps_only_the_named_items() {
+        let mylib = "pub fn used() -> u32 { helper() }
+                     fn helper() -> u32 { 1 }
+                     pub fn unused() {}
+                     pub mod math {
+                         pub fn mul(a: i64, b: i64) -> i64 { a * b }
+                         pub fn div(a: i64, b: i64) -> i64 { a / b }
+                     }
+                     pub mod graph { pub fn dfs() {} }";
+        let solution = "use mylib::math::mul; fn main() { mylib::used(); mul(1, 2); }";
+        assert_eq!(
+            kept(&[("mylib", mylib)], "mylib", solution),
+            tokens(
+                "pub fn used() -> u32 { helper() }
+                 fn helper() -> u32 { 1 }
+                 pub mod math { pub fn mul(a: i64, b: i64) -> i64 { a * b } }"
+            )
+        );
+        assert_eq!(
+            kept(&[("mylib", mylib)], "mylib", "fn main() {}"),
+            "(unused)"
+        );
+        // Paths that merely end in a module's name keep the module, but none of its items.
+        assert_eq!(
+            kept(&[("mylib", mylib)], "mylib", "mylib::x::graph::y();"),
+            tokens("pub mod graph {}")
+        );
+    }
+
+    #[test]
+    fn follows_paths_between_modules_and_libraries() {
+        let mylib = "pub mod a { pub fn f() { crate::b::g() } }
+                     pub mod b { pub fn g() { super::c::h() } }
+                     pub mod c { pub fn h() { helper::util::u() } }
+                     pub mod d { pub fn k() { e::x() } }
+                     pub mod e { pub fn x() {} }";
+        let helper = "pub mod util { pub fn u() {} pub fn v() {} }";
+        let libraries = [("mylib", mylib), ("helper", helper)];
+        let solution = "use mylib::a::f; fn main() { f() }";
+        assert_eq!(
+            kept(&libraries, "mylib", solution),
+            tokens(
+                "pub mod a { pub fn f() { crate::b::g() } }
+                 pub mod b { pub fn g() { super::c::h() } }
+                 pub mod c { pub fn h() { helper::util::u() } }"
+            )
+        );
+        assert_eq!(
+            kept(&libraries, "helper", solution),
+            tokens("pub mod util { pub fn u() {} }")
+        );
+        assert_eq!(
+            kept(&libraries, "mylib", "fn main() { mylib::d::k() }"),
+            tokens("pub mod d { pub fn k() { e::x() } } pub mod e { pub fn x() {} }")
+        );
+    }
+
+    #[test]
+    fn keeps_impls_with_their_types_and_traits() {
+        let mylib = "pub struct Modint(u32);
+                     const MOD: u32 = 998244353;
+                     impl Modint { pub fn new(x: u32) -> Self { Modint(x % MOD) } }
+                     impl std::ops::Add for Modint {
+                         type Output = Modint;
+                         fn add(self, other: Modint) -> Modint { Modint::new(self.0 + other.0) }
+                     }
+                     pub struct Unused;
+                     impl Unused { fn f() {} }
+                     impl Clone for Unused { fn clone(&self) -> Self { Unused } }
+                     pub trait Monoid { fn op(a: u32, b: u32) -> u32; }
+                     pub struct Max;
+                     impl Monoid for Max { fn op(a: u32, b: u32) -> u32 { a.max(b) } }
+                     pub trait Ext { fn twice(&self) -> Self; }
+                     impl Ext for u32 { fn twice(&self) -> u32 { self * 2 } }";
+        let libraries = [("mylib", mylib)];
+        // The trait of a method is kept even though the call does not name it.
+        let solution = "use mylib::*; fn main() { let _ = Modint::new(1) + Modint::new(2); \
+                        let _ = 3u32.twice(); }";
+        let kept_items = kept(&libraries, "mylib", solution);
+        for item in [
+            "pub struct Modint",
+            "const MOD",
+            "impl Modint",
+            "impl std::ops::Add for Modint",
+            "pub trait Ext",
+            "impl Ext for u32",
+        ] {
+            assert!(
+                kept_items.contains(item),
+                "{item} missing from {kept_items}"
+            );
+        }
+        for item in ["Unused", "Monoid", "Max"] {
+            assert!(!kept_items.contains(item), "{item} left in {kept_items}");
+        }
+
+        let kept_items = kept(&libraries, "mylib", "fn main() { mylib::Max::op(1, 2); }");
+        assert_eq!(
+            kept_items,
+            tokens(
+                "pub trait Monoid { fn op(a: u32, b: u32) -> u32; }
+                 pub struct Max;
+                 impl Monoid for Max { fn op(a: u32, b: u32) -> u32 { a.max(b) } }"
+            )
+        );
+    }
+
+    #[test]
+    fn keeps_the_imports_that_are_needed() {
+        let mylib = "use std::collections::HashMap;
+                     use std::io::Write as _;
+                     pub mod a { pub fn f() {} pub fn g() {} }
+                     use a::f;
+                     use self::a::g;
+                     use crate::a::{f as first, g as second};
+                     pub use a::*;
+                     pub fn h() { f() }";
+        assert_eq!(
+            kept(&[("mylib", mylib)], "mylib", "fn main() { mylib::h() }"),
+            tokens(
+                "use std::collections::HashMap;
+                 use std::io::Write as _;
+                 pub mod a { pub fn f() {} }
+                 use a::f;
+                 pub use a::*;
+                 pub fn h() { f() }"
+            )
+        );
+    }
+
+    #[test]
+    fn keeps_the_used_macros() {
+        let mylib = "#[macro_export]
+                     macro_rules! sq { ($x:expr) => { $crate::math::mul($x, $x) }; }
+                     #[macro_export]
+                     macro_rules! unused { () => {} }
+                     pub mod math {
+                         pub fn mul(a: i64, b: i64) -> i64 { a * b }
+                         pub fn add(a: i64, b: i64) -> i64 { a + b }
+                     }";
+        assert_eq!(
+            kept(
+                &[("mylib", mylib)],
+                "mylib",
+                "use mylib::sq; fn main() { sq!(3); }"
+            ),
+            tokens(
+                "macro_rules! sq { ($x:expr) => { $crate::math::mul($x, $x) }; }
+                 pub(crate) use sq;
+                 pub mod math { pub fn mul(a: i64, b: i64) -> i64 { a * b } }"
+            )
+        );
+    }
+
+    fn rewrite_str(source: &str) -> String {
+        rewrite(source.parse().unwrap(), "mylib", &["mylib", "helper"]).to_string()
+    }
+
+    #[test]
+    fn rewrite_points_crate_paths_into_the_library_module() {
+        assert_eq!(
+            rewrite_str("crate::a::f()"),
+            rewrite_expected("crate::mylib::a::f()")
+        );
+        assert_eq!(
+            rewrite_str("use crate::{a, b};"),
+            rewrite_expected("use crate::mylib::{a, b};")
+        );
+        assert_eq!(
+            rewrite_str("helper::util::u()"),
+            rewrite_expected("crate::helper::util::u()")
+        );
+        // Only paths starting with a library name are moved.
+        assert_eq!(
+            rewrite_str("x::helper::u()"),
+            rewrite_expected("x::helper::u()")
+        );
+        assert_eq!(
+            rewrite_str("let crate_ = 1;"),
+            rewrite_expected("let crate_ = 1;")
+        );
+    }
+
+    #[test]
+    fn rewrite_handles_dollar_crate_in_macros() {
+        let source = "macro_rules! m { ($x:expr) => { $crate::a::f($x) }; }";
+        assert_eq!(
+            rewrite_str(source),
+            rewrite_expected("macro_rules! m { ($x:expr) => { $crate::mylib::a::f($x) }; }")
+        );
+    }
+
+    #[test]
+    fn rewrite_drops_doc_comments() {
+        let source = "//! Crate docs.\n/// Item docs.\n#[inline]\n\
+                      pub fn f() {\n/** block */\nlet x = 1; }";
+        assert_eq!(
+            rewrite_str(source),
+            rewrite_expected("#[inline] pub fn f() { let x = 1; }")
+        );
+    }
+
+    fn rewrite_expected(source: &str) -> String {
+        source.parse::<TokenStream>().unwrap().to_string()
+    }
+
+    #[test]
+    fn strip_tests_drops_test_only_items() {
+        let library = library(
+            "pub fn keep() {}
+             #[cfg(test)] fn helper() {}
+             #[cfg(test)] mod tests { #[test] fn t() {} }
+             pub mod m {
+                 pub struct S;
+                 impl S { pub fn keep() {} #[cfg(test)] fn only_in_tests() {} }
+                 #[cfg(test)] use std::fmt;
+             }
+             #[cfg(not(test))] pub fn not_test() {}",
+        );
+        let items = &library.items;
+        let all = quote::quote!(#(#items)*).to_string();
+        assert!(all.contains("keep"));
+        assert!(all.contains("not_test"));
+        for gone in ["helper", "tests", "only_in_tests", "fmt"] {
+            assert!(!all.contains(gone), "{gone} left in {all}");
+        }
+    }
+
+    #[test]
+    fn unexport_macros_reexports_them_in_the_crate() {
+        let library = library(
+            "#[macro_export] macro_rules! top { () => {} }
+             pub mod inner { #[macro_export] macro_rules! nested { () => {} } }",
+        );
+        let items = &library.items;
+        assert_eq!(
+            tokens(&quote::quote!(#(#items)*).to_string()),
+            tokens(
+                "macro_rules! top { () => {} }
+                 pub(crate) use top;
+                 pub mod inner { macro_rules! nested { () => {} } pub(crate) use nested; }
+                 pub(crate) use crate::inner::nested;"
+            )
+        );
+    }
+
+    #[test]
+    fn bundles_only_the_used_items() {
+        let dir = std::env::temp_dir().join(format!("kp-test-{}-bundle", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let write = |path: &str, contents: &str| {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        };
+        write(
+            "abc/Cargo.toml",
+            "[package]\nname = \"abc\"\n\n[dependencies]\nmy-lib = { path = \"../mylib\" }\n",
+        );
+        write("mylib/Cargo.toml", "[package]\nname = \"my-lib\"\n");
+        write(
+            "mylib/src/lib.rs",
+            "//! My library.\n\
+             pub mod math;\n\
+             pub mod graph;\n\
+             /// Squares.\n\
+             #[macro_export]\n\
+             macro_rules! sq { ($x:expr) => { $crate::math::mul($x, $x) }; }\n\
+             pub fn unused_root() {}\n\
+             #[cfg(test)]\n\
+             mod tests {}\n",
+        );
+        write(
+            "mylib/src/math.rs",
+            "/// Product.\npub fn mul(a: i64, b: i64) -> i64 { a * b }\n\
+             pub fn div(a: i64, b: i64) -> i64 { a / b }\n\
+             #[cfg(test)]\nmod tests { #[test] fn t() {} }\n",
+        );
+        write("mylib/src/graph.rs", "pub fn dfs() {}\n");
+
+        let solution =
+            "extern crate my_lib;\nuse my_lib::sq;\nfn main() { println!(\"{}\", sq!(3)); }\n";
+        let bundle = bundle(&dir.join("abc"), solution).unwrap();
+        assert_eq!(bundle.libraries, [("my_lib".to_string(), 3, 6)]);
+        let source = bundle.source;
+        assert!(source.starts_with("use my_lib::sq;\n"));
+        assert!(source.contains("mod my_lib {"));
+        assert!(source.contains("pub mod math {"));
+        assert!(source.contains("$crate::my_lib::math::mul($x, $x)"));
+        assert!(source.contains("pub(crate) use sq;"));
+        for gone in [
+            "mod graph",
+            "fn div",
+            "unused_root",
+            "mod tests",
+            "macro_export",
+            "Squares",
+            "Product",
+            "My library",
+        ] {
+            assert!(!source.contains(gone), "{gone} left in:\n{source}");
+        }
+        syn::parse_file(&source).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn scratch_manifest_takes_the_libraries_registry_dependencies() {
+        let dir = std::env::temp_dir().join(format!("kp-test-{}-scratch", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let write = |path: &str, contents: &str| {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        };
+        write(
+            "abc/Cargo.toml",
+            "[package]\nname = \"abc\"\nedition = \"2018\"\n\n[dependencies]\n\
+             proconio = \"0.4\"\nrand = \"0.8\"\nmylib = { path = \"../mylib\" }\n",
+        );
+        write(
+            "mylib/Cargo.toml",
+            "[package]\nname = \"mylib\"\n\n[dependencies]\nrand = \"0.7\"\n\
+             itertools = { version = \"0.11\", default-features = false }\n\
+             util = { path = \"../util\" }\n",
+        );
+        write(
+            "util/Cargo.toml",
+            "[package]\nname = \"util\"\n\n[dependencies]\n\
+             rng = { package = \"rand_pcg\", version = \"0.3\" }\n\
+             mylib = { path = \"../mylib\" }\n",
+        );
+
+        let doc = scratch_manifest(&dir.join("abc"), "a").unwrap();
+        assert_eq!(doc["package"]["name"].as_str(), Some("bundle-a"));
+        assert_eq!(doc["package"]["edition"].as_str(), Some("2018"));
+        let dependencies = doc["dependencies"].as_table().unwrap();
+        let names: Vec<_> = dependencies.iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["proconio", "rand", "itertools", "rng"]);
+        // The contest's requirement wins over a library's.
+        assert_eq!(dependencies["rand"].as_str(), Some("0.8"));
+        assert_eq!(dependencies["rng"]["package"].as_str(), Some("rand_pcg"));
+        assert_eq!(
+            dependencies["itertools"]["default-features"].as_bool(),
+            Some(false)
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
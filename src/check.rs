@@ -0,0 +1,591 @@
+//! Checking a contest against the crates available on the judge.
+//!
+//! The judge builds submissions with a fixed set of crates, so a dependency it does not
+//! have (or has in another version) otherwise only shows up as a compile error after
+//! submitting. The `[dependencies]` of the contest, and of the local libraries `kp bundle`
+//! inlines, are compared with that set, as are the crates named by `use` in each bin.
+
+use anyhow::{bail, Context, Result};
+use semver::{Version, VersionReq};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::Path,
+};
+use syn::{ImplItem, Item, Stmt, UseTree};
+use toml_edit::{DocumentMut, Item as TomlItem};
+
+/// The crate list shipped with kp.
+const BUILTIN_CRATES: &str = include_str!("judge_crates.toml");
+
+/// Crates of the Rust distribution, which are always there.
+const DISTRIBUTION_CRATES: [&str; 5] = ["std", "core", "alloc", "proc_macro", "test"];
+
+/// Path roots that are not crate names.
+const PATH_KEYWORDS: [&str; 4] = ["crate", "self", "super", "Self"];
+
+/// The crates the judge offers.
+pub struct JudgeCrates {
+    /// Language the list belongs to, for messages.
+    pub language: String,
+    /// The crates by package name with `-` replaced by `_`.
+    crates: BTreeMap<String, JudgeCrate>,
+}
+
+struct JudgeCrate {
+    name: String,
+    version: Version,
+    /// Name the crate is used by in code.
+    lib: String,
+}
+
+impl JudgeCrates {
+    /// `judge-crates.toml` in the kp config directory, else the built-in list.
+    pub fn load() -> Result<JudgeCrates> {
+        let custom = crate::config_dir()
+            .map(|dir| dir.join("judge-crates.toml"))
+            .filter(|path| path.is_file());
+        match custom {
+            Some(path) => {
+                let text = fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                JudgeCrates::parse(&text)
+                    .with_context(|| format!("{} is not a valid crate list", path.display()))
+            }
+            None => JudgeCrates::parse(BUILTIN_CRATES),
+        }
+    }
+
+    fn parse(text: &str) -> Result<JudgeCrates> {
+        let doc = text.parse::<DocumentMut>()?;
+        let language = doc
+            .get("language")
+            .and_then(TomlItem::as_str)
+            .unwrap_or("the judge")
+            .to_string();
+        let table = doc
+            .get("crates")
+            .and_then(TomlItem::as_table_like)
+            .context("no [crates] table")?;
+        let mut crates = BTreeMap::new();
+        // `name = "version"`, or `name = { version = "...", lib = "..." }` when the crate is
+        // used under another name (`ac-library-rs` is `ac_library`).
+        for (name, spec) in table.iter() {
+            let version = spec
+                .as_str()
+                .or_else(|| spec.get("version")?.as_str())
+                .with_context(|| format!("no version for {name}"))?;
+            let version = Version::parse(version)
+                .with_context(|| format!("{version} (of {name}) is not a version"))?;
+            let lib = spec.get("lib").and_then(TomlItem::as_str);
+            let lib = lib.map_or_else(|| name.replace('-', "_"), str::to_string);
+            let name = name.to_string();
+            crates.insert(name.replace('-', "_"), JudgeCrate { name, version, lib });
+        }
+        Ok(JudgeCrates { language, crates })
+    }
+
+    fn get(&self, package: &str) -> Option<&JudgeCrate> {
+        self.crates.get(&package.replace('-', "_"))
+    }
+
+    /// The crate used as `lib` in code.
+    fn by_lib(&self, lib: &str) -> Option<&JudgeCrate> {
+        self.crates
+            .values()
+            .find(|judge_crate| judge_crate.lib == lib)
+    }
+}
+
+/// How a crate name used in the code is provided.
+enum Provider {
+    /// A registry dependency, by package name.
+    Registry(String),
+    /// A local library, inlined by `kp bundle`.
+    Local,
+}
+
+/// Warnings about everything in the contest in `dir` the judge cannot build, limited to the
+/// bin `problem` if given.
+pub fn check(dir: &Path, problem: Option<&str>, judge: &JudgeCrates) -> Result<Vec<String>> {
+    let mut warnings = Vec::new();
+
+    let providers = check_manifest(dir, "Cargo.toml", judge, &mut warnings)?;
+    // The local libraries end up in the submission too, and so do their dependencies.
+    let mut seen = BTreeSet::new();
+    let mut pending = crate::bundle::path_dependencies(dir)?;
+    while let Some((name, path)) = pending.pop() {
+        if !seen.insert(name) {
+            continue;
+        }
+        let label = relative(dir, &path.join("Cargo.toml"));
+        check_manifest(&path, &label, judge, &mut warnings)?;
+        pending.extend(crate::bundle::path_dependencies(&path)?);
+    }
+
+    let bins = manifest_bins(dir)?;
+    let bins: Vec<_> = match problem {
+        Some(problem) => bins
+            .into_iter()
+            .filter(|(name, _)| name == problem)
+            .collect(),
+        None => bins,
+    };
+    if bins.is_empty() {
+        bail!("no bin to check in {}", dir.join("Cargo.toml").display());
+    }
+    for (_, path) in bins {
+        let source = dir.join(&path);
+        if !source.exists() {
+            continue;
+        }
+        let file = fs::read_to_string(&source)
+            .with_context(|| format!("Failed to read {}", source.display()))?;
+        // A half-written bin must not keep the others from being checked.
+        let file = match syn::parse_file(&file) {
+            Ok(file) => file,
+            Err(err) => {
+                warnings.push(format!("{path}: not checked, as it does not parse: {err}"));
+                continue;
+            }
+        };
+        let (mut roots, mut locals) = (BTreeSet::new(), BTreeSet::new());
+        collect_items(&file.items, &mut roots, &mut locals);
+
+        for root in roots {
+            let root = root.as_str();
+            if DISTRIBUTION_CRATES.contains(&root)
+                || PATH_KEYWORDS.contains(&root)
+                || locals.contains(root)
+            {
+                continue;
+            }
+            match providers.get(root) {
+                Some(Provider::Local) => {}
+                Some(Provider::Registry(package)) if judge.get(package).is_none() => warnings.push(
+                    format!("{path}: uses `{root}`, which the judge does not have"),
+                ),
+                Some(Provider::Registry(_)) => {}
+                None if judge.by_lib(root).is_some() => warnings.push(format!(
+                    "{path}: uses `{root}`, which the judge has but [dependencies] lacks"
+                )),
+                None => warnings.push(format!(
+                    "{path}: uses `{root}`, which is neither a dependency nor on the judge"
+                )),
+            }
+        }
+    }
+    Ok(warnings)
+}
+
+/// Check the `[dependencies]` of the manifest in `dir`, reporting problems as `label`, and
+/// return how each name usable from the code is provided.
+fn check_manifest(
+    dir: &Path,
+    label: &str,
+    judge: &JudgeCrates,
+    warnings: &mut Vec<String>,
+) -> Result<BTreeMap<String, Provider>> {
+    let manifest_path = dir.join("Cargo.toml");
+    let manifest = fs::read_to_string(&manifest_path)
+        .with_context(|| format!("Failed to read {}", manifest_path.display()))?
+        .parse::<DocumentMut>()?;
+    let mut providers = BTreeMap::new();
+    let Some(table) = manifest
+        .get("dependencies")
+        .and_then(TomlItem::as_table_like)
+    else {
+        return Ok(providers);
+    };
+    for (key, spec) in table.iter() {
+        let name = key.replace('-', "_");
+        if spec.get("path").is_some() {
+            providers.insert(name, Provider::Local);
+            continue;
+        }
+        let package = spec
+            .get("package")
+            .and_then(TomlItem::as_str)
+            .unwrap_or(key);
+        providers.insert(name, Provider::Registry(package.to_string()));
+        if let Some(judge_crate) = judge.get(package) {
+            providers.insert(
+                judge_crate.lib.clone(),
+                Provider::Registry(package.to_string()),
+            );
+        }
+
+        let Some(JudgeCrate {
+            name: judge_name,
+            version,
+            ..
+        }) = judge.get(package)
+        else {
+            warnings.push(format!(
+                "{label}: `{package}` is not available on the judge"
+            ));
+            continue;
+        };
+        if spec.get("git").is_some() {
+            warnings.push(format!(
+                "{label}: `{package}` comes from git; the judge has {judge_name} {version}"
+            ));
+            continue;
+        }
+        let requirement = spec.as_str().or_else(|| spec.get("version")?.as_str());
+        let Some(requirement) = requirement else {
+            continue;
+        };
+        match VersionReq::parse(requirement) {
+            Ok(req) if req.matches(version) => {}
+            Ok(_) => warnings.push(format!(
+                "{label}: `{package} = \"{requirement}\"` does not match the judge's \
+                 {judge_name} {version}"
+            )),
+            Err(err) => warnings.push(format!("{label}: `{package} = \"{requirement}\"`: {err}")),
+        }
+    }
+    Ok(providers)
+}
+
+/// `(name, path)` of each `[[bin]]` in the manifest in `dir`.
+fn manifest_bins(dir: &Path) -> Result<Vec<(String, String)>> {
+    let manifest_path = dir.join("Cargo.toml");
+    let manifest = fs::read_to_string(&manifest_path)
+        .with_context(|| format!("Failed to read {}", manifest_path.display()))?
+        .parse::<DocumentMut>()?;
+    let Some(bins) = manifest.get("bin").and_then(TomlItem::as_array_of_tables) else {
+        return Ok(Vec::new());
+    };
+    Ok(bins
+        .iter()
+        .filter_map(|bin| {
+            let name = bin.get("name")?.as_str()?.to_string();
+            let path = bin
+                .get("path")
+                .and_then(TomlItem::as_str)
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("src/bin/{name}.rs"));
+            Some((name, path))
+        })
+        .collect())
+}
+
+/// Collect the first segments of `use` paths (and `extern crate`s) into `roots` and the
+/// names defined by the items into `locals`, looking into inline modules and fn bodies.
+fn collect_items<'a>(
+    items: impl IntoIterator<Item = &'a Item>,
+    roots: &mut BTreeSet<String>,
+    locals: &mut BTreeSet<String>,
+) {
+    for item in items {
+        let ident = match item {
+            Item::Use(item) => {
+                use_roots(&item.tree, roots);
+                None
+            }
+            Item::ExternCrate(item) => {
+                roots.insert(item.ident.to_string());
+                item.rename.as_ref().map(|(_, rename)| rename)
+            }
+            Item::Mod(item) => {
+                if let Some((_, items)) = &item.content {
+                    collect_items(items, roots, locals);
+                }
+                Some(&item.ident)
+            }
+            Item::Fn(item) => {
+                collect_items(block_items(&item.block.stmts), roots, locals);
+                Some(&item.sig.ident)
+            }
+            Item::Impl(item) => {
+                for impl_item in &item.items {
+                    if let ImplItem::Fn(method) = impl_item {
+                        collect_items(block_items(&method.block.stmts), roots, locals);
+                    }
+                }
+                None
+            }
+            Item::Struct(item) => Some(&item.ident),
+            Item::Enum(item) => Some(&item.ident),
+            Item::Union(item) => Some(&item.ident),
+            Item::Trait(item) => Some(&item.ident),
+            Item::Type(item) => Some(&item.ident),
+            Item::Const(item) => Some(&item.ident),
+            Item::Static(item) => Some(&item.ident),
+            Item::Macro(item) => item.ident.as_ref(),
+            _ => None,
+        };
+        if let Some(ident) = ident {
+            locals.insert(ident.to_string());
+        }
+    }
+}
+
+fn block_items(stmts: &[Stmt]) -> impl Iterator<Item = &Item> {
+    stmts.iter().filter_map(|stmt| match stmt {
+        Stmt::Item(item) => Some(item),
+        _ => None,
+    })
+}
+
+fn use_roots(tree: &UseTree, roots: &mut BTreeSet<String>) {
+    match tree {
+        UseTree::Path(path) => {
+            roots.insert(path.ident.to_string());
+        }
+        UseTree::Name(name) => {
+            roots.insert(name.ident.to_string());
+        }
+        UseTree::Rename(rename) => {
+            roots.insert(rename.ident.to_string());
+        }
+        UseTree::Group(group) => {
+            for tree in &group.items {
+                use_roots(tree, roots);
+            }
+        }
+        UseTree::Glob(_) => {}
+    }
+}
+
+/// `path` relative to `base` when it lies below it, for messages.
+fn relative(base: &Path, path: &Path) -> String {
+    path.strip_prefix(base)
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// A scratch directory named `name` holding `files` as `(path, contents)`.
+    fn scratch(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("kp-test-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        for (path, contents) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        dir
+    }
+
+    /// A contest with the bins `a` and `b` and `dependencies` as its `[dependencies]`.
+    fn contest(name: &str, dependencies: &str, a: &str, b: &str) -> PathBuf {
+        let manifest = format!(
+            "[package]\nname = \"abc\"\n\n\
+             [[bin]]\nname = \"a\"\npath = \"src/bin/a.rs\"\n\n\
+             [[bin]]\nname = \"b\"\npath = \"src/bin/b.rs\"\n\n\
+             [dependencies]\n{dependencies}"
+        );
+        let files = [
+            ("abc/Cargo.toml", manifest.as_str()),
+            ("abc/src/bin/a.rs", a),
+            ("abc/src/bin/b.rs", b),
+        ];
+        scratch(name, &files).join("abc")
+    }
+
+    fn check_contest(dir: &Path) -> Vec<String> {
+        let judge = JudgeCrates::parse(BUILTIN_CRATES).unwrap();
+        let warnings = check(dir, None, &judge).unwrap();
+        fs::remove_dir_all(dir.parent().unwrap()).unwrap();
+        warnings
+    }
+
+    #[test]
+    fn parses_the_builtin_list() {
+        let judge = JudgeCrates::parse(BUILTIN_CRATES).unwrap();
+        assert_eq!(judge.language, "Rust (rustc 1.70.0)");
+        let proconio = judge.get("proconio").unwrap();
+        assert_eq!(proconio.version, Version::new(0, 4, 5));
+        assert_eq!(proconio.lib, "proconio");
+        // Package names are looked up with `-` and `_` alike.
+        assert_eq!(judge.get("num_traits").unwrap().name, "num-traits");
+        assert_eq!(judge.by_lib("num_traits").unwrap().name, "num-traits");
+        assert!(judge.get("serde").is_none());
+    }
+
+    #[test]
+    fn rejects_malformed_lists() {
+        assert!(JudgeCrates::parse("language = \"x\"\n").is_err());
+        assert!(JudgeCrates::parse("[crates]\nfoo = \"1.0\"\nbar = \"latest\"\n").is_err());
+        assert!(JudgeCrates::parse("[crates]\nfoo = { lib = \"bar\" }\n").is_err());
+        let judge = JudgeCrates::parse("[crates]\nfoo = \"1.0.0\"\n").unwrap();
+        assert_eq!(judge.language, "the judge");
+    }
+
+    #[test]
+    fn ac_library_rs_is_used_as_ac_library() {
+        let judge = JudgeCrates::parse(BUILTIN_CRATES).unwrap();
+        assert_eq!(judge.get("ac-library-rs").unwrap().lib, "ac_library");
+        assert_eq!(judge.by_lib("ac_library").unwrap().name, "ac-library-rs");
+        assert!(judge.by_lib("ac_library_rs").is_none());
+
+        let dir = contest(
+            "check-acl",
+            "ac-library-rs = \"0.1.1\"\n",
+            "use ac_library::Dsu;\nfn main() {}\n",
+            "fn main() {}\n",
+        );
+        assert_eq!(check_contest(&dir), Vec::<String>::new());
+        let dir = contest(
+            "check-acl-missing",
+            "",
+            "use ac_library::Dsu;\nfn main() {}\n",
+            "fn main() {}\n",
+        );
+        assert_eq!(
+            check_contest(&dir),
+            ["src/bin/a.rs: uses `ac_library`, which the judge has but [dependencies] lacks"]
+        );
+    }
+
+    #[test]
+    fn warns_about_versions_the_judge_does_not_have() {
+        let dir = contest(
+            "check-versions",
+            "proconio = \"0.3\"\nitertools = \"0.11\"\nrand = \"=0.8.4\"\n\
+             num = \">=0.4, <0.5\"\nbitvec = \"one\"\n",
+            "fn main() {}\n",
+            "fn main() {}\n",
+        );
+        let warnings = check_contest(&dir);
+        assert_eq!(
+            warnings[..2],
+            [
+                "Cargo.toml: `proconio = \"0.3\"` does not match the judge's proconio 0.4.5",
+                "Cargo.toml: `rand = \"=0.8.4\"` does not match the judge's rand 0.8.5",
+            ]
+        );
+        // A requirement that does not parse is reported as such.
+        assert_eq!(warnings.len(), 3, "{warnings:#?}");
+        assert!(warnings[2].starts_with("Cargo.toml: `bitvec = \"one\"`: "));
+    }
+
+    #[test]
+    fn follows_package_renames() {
+        let dir = contest(
+            "check-renames",
+            "rng = { package = \"rand\", version = \"0.8\" }\n\
+             fast = { package = \"fastrand\", version = \"2\" }\n",
+            "use rng::Rng;\nuse rand::random;\nfn main() {}\n",
+            "use fast::u32;\nfn main() {}\n",
+        );
+        assert_eq!(
+            check_contest(&dir),
+            [
+                "Cargo.toml: `fastrand` is not available on the judge",
+                "src/bin/b.rs: uses `fast`, which the judge does not have",
+            ]
+        );
+    }
+
+    #[test]
+    fn warns_about_git_dependencies() {
+        let dir = contest(
+            "check-git",
+            "proconio = { git = \"https://github.com/statiolake/proconio-rs\" }\n",
+            "use proconio::input;\nfn main() {}\n",
+            "fn main() {}\n",
+        );
+        assert_eq!(
+            check_contest(&dir),
+            ["Cargo.toml: `proconio` comes from git; the judge has proconio 0.4.5"]
+        );
+    }
+
+    #[test]
+    fn tells_local_modules_from_external_crates() {
+        let a = "use std::io::Read;\n\
+                 use self::util::twice;\n\
+                 use util::*;\n\
+                 use Kind::{Big, Small};\n\
+                 use {itertools::Itertools, superslice::Ext as _};\n\
+                 mod util { pub fn twice(x: u32) -> u32 { x * 2 } }\n\
+                 enum Kind { Big, Small }\n\
+                 fn main() { use serde::Deserialize; }\n";
+        let b = "extern crate alloc;\nextern crate regex as re;\nuse re::Regex;\nfn main() {}\n";
+        let dir = contest("check-roots", "itertools = \"0.11\"\n", a, b);
+        assert_eq!(
+            check_contest(&dir),
+            [
+                "src/bin/a.rs: uses `serde`, which is neither a dependency nor on the judge",
+                "src/bin/a.rs: uses `superslice`, which the judge has but [dependencies] lacks",
+                "src/bin/b.rs: uses `regex`, which the judge has but [dependencies] lacks",
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_the_dependencies_of_path_libraries() {
+        let dir = scratch(
+            "check-libraries",
+            &[
+                (
+                    "abc/Cargo.toml",
+                    "[package]\nname = \"abc\"\n\n\
+                     [[bin]]\nname = \"a\"\npath = \"src/bin/a.rs\"\n\n\
+                     [dependencies]\nmy-lib = { path = \"../mylib\" }\n",
+                ),
+                ("abc/src/bin/a.rs", "use my_lib::solve;\nfn main() {}\n"),
+                (
+                    "mylib/Cargo.toml",
+                    "[package]\nname = \"my-lib\"\n\n[dependencies]\nrand = \"0.7\"\n\
+                     util = { path = \"../util\" }\n",
+                ),
+                (
+                    "util/Cargo.toml",
+                    "[package]\nname = \"util\"\n\n[dependencies]\nserde = \"1\"\n\
+                     my-lib = { path = \"../mylib\" }\n",
+                ),
+            ],
+        );
+        assert_eq!(
+            check_contest(&dir.join("abc")),
+            [
+                "../mylib/Cargo.toml: `rand = \"0.7\"` does not match the judge's rand 0.8.5",
+                "../mylib/../util/Cargo.toml: `serde` is not available on the judge",
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_bins_that_do_not_parse() {
+        let dir = contest(
+            "check-unparsable",
+            "",
+            "fn main() {\n    let x = \n",
+            "use serde::Serialize;\nfn main() {}\n",
+        );
+        let warnings = check_contest(&dir);
+        assert_eq!(warnings.len(), 2, "{warnings:#?}");
+        assert!(warnings[0].starts_with("src/bin/a.rs: not checked, as it does not parse: "));
+        assert_eq!(
+            warnings[1],
+            "src/bin/b.rs: uses `serde`, which is neither a dependency nor on the judge"
+        );
+    }
+
+    #[test]
+    fn checks_only_the_given_problem() {
+        let dir = contest(
+            "check-problem",
+            "",
+            "use serde::Serialize;\nfn main() {}\n",
+            "use regex::Regex;\nfn main() {}\n",
+        );
+        let judge = JudgeCrates::parse(BUILTIN_CRATES).unwrap();
+        assert_eq!(
+            check(&dir, Some("b"), &judge).unwrap(),
+            ["src/bin/b.rs: uses `regex`, which the judge has but [dependencies] lacks"]
+        );
+        let err = check(&dir, Some("z"), &judge).err().unwrap();
+        assert!(err.to_string().starts_with("no bin to check in "));
+        fs::remove_dir_all(dir.parent().unwrap()).unwrap();
+    }
+}
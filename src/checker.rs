@@ -0,0 +1,168 @@
+//! Special judges for problems that accept more than one correct output.
+//!
+//! A checker follows the testlib convention: it is invoked as
+//! `checker <input-file> <output-file> <answer-file>` and exits with status 0 when the
+//! output is accepted, 1 (wrong answer) or 2 (presentation error) when it is rejected.
+//! Anything it prints is shown as the reason for a rejection. Any other outcome, such as
+//! testlib's 3 (the checker itself failed), a crash or running out of time, is an error.
+
+use anyhow::{bail, Result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::runner::{resolve_problem_bin, run_binary_with_args};
+
+/// How long a checker may take on one case before it is considered stuck.
+const CHECKER_TIME_LIMIT: Duration = Duration::from_secs(10);
+
+/// A built checker program for one problem.
+pub struct Checker {
+    exe: PathBuf,
+    dir: PathBuf,
+    work_dir: PathBuf,
+}
+
+/// Verdict of a checker on a single output.
+pub struct CheckOutcome {
+    pub accepted: bool,
+    pub message: String,
+}
+
+impl Checker {
+    /// Locate and build the checker for `problem` in the contest at `dir`.
+    ///
+    /// `spec` (from `--checker`) is either a path to an executable or the name of a bin
+    /// in the contest manifest. Without it, `testcases/<problem>/checker.rs` is used if
+    /// that file exists.
+    pub fn resolve(dir: &Path, problem: &str, spec: Option<&str>) -> Result<Option<Checker>> {
+        let Some(exe) = resolve_problem_bin(dir, problem, "checker", spec)? else {
+            return Ok(None);
+        };
+
+        let work_dir = dir.join("target").join("kp").join("check").join(problem);
+        fs::create_dir_all(&work_dir)?;
+        Ok(Some(Checker {
+            exe,
+            dir: dir.to_path_buf(),
+            work_dir: fs::canonicalize(work_dir)?,
+        }))
+    }
+
+    /// Run the checker on one case. `case` names the temporary files handed to it.
+    pub fn check(
+        &self,
+        case: &str,
+        input: &str,
+        actual: &str,
+        expected: &str,
+    ) -> Result<CheckOutcome> {
+        let input_path = self.work_dir.join(format!("{case}.in"));
+        let output_path = self.work_dir.join(format!("{case}.out"));
+        let answer_path = self.work_dir.join(format!("{case}.ans"));
+        fs::write(&input_path, input)?;
+        fs::write(&output_path, actual)?;
+        fs::write(&answer_path, expected)?;
+
+        let args = [input_path, output_path, answer_path].map(|p| p.display().to_string());
+        let execution =
+            run_binary_with_args(&self.dir, &self.exe, &args, "", Some(CHECKER_TIME_LIMIT))?;
+
+        let mut message = execution.stdout.trim().to_string();
+        if !execution.stderr.trim().is_empty() {
+            if !message.is_empty() {
+                message.push('\n');
+            }
+            message.push_str(execution.stderr.trim());
+        }
+        let checker = self.exe.display();
+        if execution.timed_out {
+            bail!(
+                "checker {} took longer than {:?} on {}",
+                checker,
+                CHECKER_TIME_LIMIT,
+                case
+            );
+        }
+        let reason = match execution.status.code() {
+            Some(0..=2) => None,
+            Some(3) => Some("FAIL".to_string()),
+            _ => execution.failure_reason(),
+        };
+        if let Some(reason) = reason {
+            let details = if message.is_empty() {
+                String::new()
+            } else {
+                format!(": {message}")
+            };
+            bail!(
+                "checker {} failed on {} ({}){}",
+                checker,
+                case,
+                reason,
+                details
+            );
+        }
+        let accepted = execution.status.success();
+        if !accepted && message.is_empty() {
+            message = format!("checker exited with {}", execution.status);
+        }
+        Ok(CheckOutcome { accepted, message })
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    /// A checker running the shell script `body`, in a scratch directory named `name`.
+    fn script_checker(name: &str, body: &str) -> Checker {
+        let dir = std::env::temp_dir().join(format!("kp-test-{}-{name}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let exe = dir.join("checker.sh");
+        fs::write(&exe, format!("#!/bin/sh\n{body}\n")).unwrap();
+        fs::set_permissions(&exe, fs::Permissions::from_mode(0o755)).unwrap();
+        Checker {
+            exe,
+            dir: dir.clone(),
+            work_dir: dir,
+        }
+    }
+
+    #[test]
+    fn accepts_on_zero() {
+        let checker = script_checker("checker-ac", "cmp -s \"$2\" \"$3\"");
+        assert!(checker.check("c", "1\n", "2\n", "2\n").unwrap().accepted);
+        let outcome = checker.check("c", "1\n", "3\n", "2\n").unwrap();
+        assert!(!outcome.accepted);
+        assert_eq!(outcome.message, "checker exited with exit status: 1");
+    }
+
+    #[test]
+    fn rejects_on_wrong_answer_and_presentation_error() {
+        let checker = script_checker("checker-wa", "echo 'expected 2, found 3'; exit 1");
+        let outcome = checker.check("c", "", "3\n", "2\n").unwrap();
+        assert!(!outcome.accepted);
+        assert_eq!(outcome.message, "expected 2, found 3");
+        let checker = script_checker("checker-pe", "echo 'extra token' >&2; exit 2");
+        let outcome = checker.check("c", "", "2 2\n", "2\n").unwrap();
+        assert!(!outcome.accepted);
+        assert_eq!(outcome.message, "extra token");
+    }
+
+    #[test]
+    fn fail_and_crashes_are_errors() {
+        let checker = script_checker("checker-fail", "echo 'answer is invalid' >&2; exit 3");
+        let err = checker.check("c", "", "", "").err().unwrap().to_string();
+        assert!(
+            err.contains("checker.sh failed on c (FAIL): answer is invalid"),
+            "{err}"
+        );
+        let checker = script_checker("checker-crash", "kill -SEGV $$");
+        let err = checker.check("c", "", "", "").err().unwrap().to_string();
+        assert!(err.contains("killed by SIGSEGV"), "{err}");
+    }
+}
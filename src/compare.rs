@@ -0,0 +1,329 @@
+//! Comparison of a solution's output against the expected output.
+
+use std::io::IsTerminal;
+
+/// Number of unchanged lines shown before the first difference in a diff.
+const DIFF_CONTEXT: usize = 3;
+/// Maximum number of lines shown in a diff, starting at the first difference.
+const DIFF_LINES: usize = 20;
+
+/// Compares outputs line by line and token by token.
+///
+/// Line endings and trailing whitespace on each line are normalized, and trailing
+/// empty lines are ignored. Non-numeric tokens must match exactly. When `error` is
+/// set, tokens that parse as numbers on both sides are accepted if their absolute or
+/// relative error is within it.
+#[derive(Clone, Copy, Default)]
+pub struct Comparator {
+    pub error: Option<f64>,
+}
+
+/// Position of the first difference between two outputs (0-based).
+#[derive(Clone, Copy)]
+pub struct Mismatch {
+    pub line: usize,
+    /// First differing token in that line; `None` when the line is missing on one side.
+    pub token: Option<usize>,
+}
+
+impl Comparator {
+    /// Find where `actual` first deviates from `expected`.
+    pub fn first_mismatch(&self, actual: &str, expected: &str) -> Option<Mismatch> {
+        let actual = normalize(actual);
+        let expected = normalize(expected);
+        for line in 0..actual.len().max(expected.len()) {
+            match (actual.get(line), expected.get(line)) {
+                (Some(a), Some(e)) => {
+                    if let Some(token) = self.first_token_mismatch(a, e) {
+                        return Some(Mismatch {
+                            line,
+                            token: Some(token),
+                        });
+                    }
+                }
+                _ => return Some(Mismatch { line, token: None }),
+            }
+        }
+        None
+    }
+
+    /// Render a unified diff of `actual` against `expected` around the first difference.
+    ///
+    /// Expected lines are prefixed with `-`, actual lines with `+`, and the first
+    /// differing token is highlighted when `color` is enabled.
+    pub fn diff(&self, actual: &str, expected: &str, color: bool) -> Option<String> {
+        let mismatch = self.first_mismatch(actual, expected)?;
+        let actual = normalize(actual);
+        let expected = normalize(expected);
+        let total = actual.len().max(expected.len());
+        let start = mismatch.line.saturating_sub(DIFF_CONTEXT);
+        let end = total.min(mismatch.line + DIFF_LINES);
+
+        let mut out = String::new();
+        out.push_str(&match mismatch.token {
+            Some(token) => format!(
+                "first difference at line {}, token {} (- expected, + actual)\n",
+                mismatch.line + 1,
+                token + 1
+            ),
+            None => format!(
+                "first difference at line {} (- expected, + actual)\n",
+                mismatch.line + 1
+            ),
+        });
+        for line in start..end {
+            let (a, e) = (actual.get(line), expected.get(line));
+            let differs = match (a, e) {
+                (Some(a), Some(e)) => self.first_token_mismatch(a, e).is_some(),
+                _ => true,
+            };
+            if !differs {
+                out.push_str(&format!("  {:>4} | {}\n", line + 1, e.unwrap()));
+                continue;
+            }
+            let token = if line == mismatch.line {
+                mismatch.token
+            } else {
+                None
+            };
+            if let Some(e) = e {
+                out.push_str(&paint_line('-', line, e, token, GREEN, color));
+            }
+            if let Some(a) = a {
+                out.push_str(&paint_line('+', line, a, token, RED, color));
+            }
+        }
+        if end < total {
+            out.push_str(&format!("  ... ({} more lines)\n", total - end));
+        }
+        Some(out)
+    }
+
+    fn first_token_mismatch(&self, actual: &str, expected: &str) -> Option<usize> {
+        let mut actual = actual.split_whitespace();
+        let mut expected = expected.split_whitespace();
+        for index in 0.. {
+            match (actual.next(), expected.next()) {
+                (None, None) => return None,
+                (Some(a), Some(e)) if self.token_matches(a, e) => {}
+                _ => return Some(index),
+            }
+        }
+        unreachable!()
+    }
+
+    fn token_matches(&self, actual: &str, expected: &str) -> bool {
+        if actual == expected {
+            return true;
+        }
+        let Some(error) = self.error else {
+            return false;
+        };
+        match (parse_number(actual), parse_number(expected)) {
+            (Some(a), Some(e)) => {
+                let diff = (a - e).abs();
+                diff <= error || diff <= error * e.abs()
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Split into lines, dropping `\r`, trailing whitespace and trailing empty lines.
+fn normalize(text: &str) -> Vec<&str> {
+    let mut lines: Vec<&str> = text.lines().map(str::trim_end).collect();
+    while lines.last() == Some(&"") {
+        lines.pop();
+    }
+    lines
+}
+
+/// Parse a finite number; `inf`/`nan` are treated as ordinary words.
+fn parse_number(token: &str) -> Option<f64> {
+    token.parse::<f64>().ok().filter(|v| v.is_finite())
+}
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const REVERSE: &str = "\x1b[7m";
+const RESET: &str = "\x1b[0m";
+
+/// Whether diffs written to stdout should be colored.
+pub fn use_color() -> bool {
+    std::env::var_os("NO_COLOR").is_none() && std::io::stdout().is_terminal()
+}
+
+/// Format one diff line, highlighting token number `token` if given.
+fn paint_line(
+    sign: char,
+    line: usize,
+    text: &str,
+    token: Option<usize>,
+    color_code: &str,
+    color: bool,
+) -> String {
+    if !color {
+        return format!("{sign} {:>4} | {text}\n", line + 1);
+    }
+    let body = match token.and_then(|t| token_span(text, t)) {
+        Some((from, to)) => format!(
+            "{}{REVERSE}{}{RESET}{color_code}{}",
+            &text[..from],
+            &text[from..to],
+            &text[to..]
+        ),
+        None => text.to_string(),
+    };
+    format!("{color_code}{sign} {:>4} | {body}{RESET}\n", line + 1)
+}
+
+/// Byte range of the `index`-th whitespace separated token of `text`.
+fn token_span(text: &str, index: usize) -> Option<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices() {
+        match (c.is_whitespace(), start) {
+            (true, Some(s)) => {
+                spans.push((s, i));
+                start = None;
+            }
+            (false, None) => start = Some(i),
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        spans.push((s, text.len()));
+    }
+    spans.get(index).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXACT: Comparator = Comparator { error: None };
+
+    #[test]
+    fn ignores_line_endings_and_trailing_whitespace() {
+        assert!(EXACT.first_mismatch("1 2\r\n3\r\n", "1 2\n3\n").is_none());
+        assert!(EXACT.first_mismatch("1 2   \n3\t\n", "1 2\n3").is_none());
+        assert!(EXACT.first_mismatch("1\n3\n\n\n", "1\n3\n").is_none());
+        assert!(EXACT.first_mismatch("1\n3", "1\n3\n\n").is_none());
+    }
+
+    #[test]
+    fn reports_the_first_differing_token() {
+        let mismatch = EXACT
+            .first_mismatch("1 2\n3 4 5\n", "1 2\n3 9 5\n")
+            .unwrap();
+        assert_eq!((mismatch.line, mismatch.token), (1, Some(1)));
+        // An extra token is a difference too.
+        let mismatch = EXACT.first_mismatch("1 2 3\n", "1 2\n").unwrap();
+        assert_eq!((mismatch.line, mismatch.token), (0, Some(2)));
+        // Leading whitespace only separates tokens.
+        assert!(EXACT.first_mismatch("  1  2\n", "1 2\n").is_none());
+    }
+
+    #[test]
+    fn reports_a_missing_line() {
+        let mismatch = EXACT.first_mismatch("1\n2\n", "1\n2\n3\n").unwrap();
+        assert_eq!((mismatch.line, mismatch.token), (2, None));
+        let mismatch = EXACT.first_mismatch("1\n2\n3\n", "1\n2\n").unwrap();
+        assert_eq!((mismatch.line, mismatch.token), (2, None));
+    }
+
+    #[test]
+    fn diff_marks_expected_and_actual_lines() {
+        assert!(EXACT.diff("1\r\n2  \n\n", "1\n2\n", false).is_none());
+        let diff = EXACT.diff("1\n2\n4\n", "1\n2\n3\n", false).unwrap();
+        assert_eq!(
+            diff,
+            "first difference at line 3, token 1 (- expected, + actual)\n\
+             \x20    1 | 1\n\
+             \x20    2 | 2\n\
+             -    3 | 3\n\
+             +    3 | 4\n"
+        );
+        let diff = EXACT.diff("1\n", "1\n2\n", false).unwrap();
+        assert_eq!(
+            diff,
+            "first difference at line 2 (- expected, + actual)\n\
+             \x20    1 | 1\n\
+             -    2 | 2\n"
+        );
+    }
+
+    #[test]
+    fn diff_highlights_the_differing_token() {
+        let diff = EXACT.diff("1 5 3\n", "1 2 3\n", true).unwrap();
+        assert!(diff.contains(&format!(
+            "{GREEN}-    1 | 1 {REVERSE}2{RESET}{GREEN} 3{RESET}\n"
+        )));
+        assert!(diff.contains(&format!(
+            "{RED}+    1 | 1 {REVERSE}5{RESET}{RED} 3{RESET}\n"
+        )));
+    }
+
+    #[test]
+    fn diff_is_cut_after_a_limited_number_of_lines() {
+        let expected: String = (0..100).map(|i| format!("{i}\n")).collect();
+        let actual: String = (0..100).map(|i| format!("{}\n", i + 1)).collect();
+        let diff = EXACT.diff(&actual, &expected, false).unwrap();
+        assert!(diff.ends_with("  ... (80 more lines)\n"));
+    }
+
+    #[test]
+    fn absolute_error() {
+        let comparator = Comparator { error: Some(1e-6) };
+        assert!(comparator.first_mismatch("0.0000005\n", "0\n").is_none());
+        assert!(comparator.first_mismatch("0.000002\n", "0\n").is_some());
+    }
+
+    #[test]
+    fn relative_error() {
+        let comparator = Comparator { error: Some(1e-6) };
+        // Far beyond the absolute error, but within the relative one.
+        assert!(comparator
+            .first_mismatch("1000000.5\n", "1000000\n")
+            .is_none());
+        assert!(comparator
+            .first_mismatch("1000002\n", "1000000\n")
+            .is_some());
+        // Relative to the expected value, not the actual one.
+        let comparator = Comparator { error: Some(0.5) };
+        assert!(comparator.first_mismatch("100\n", "200\n").is_none());
+        assert!(comparator.first_mismatch("200\n", "100\n").is_some());
+    }
+
+    #[test]
+    fn numbers_must_match_exactly_without_error() {
+        assert!(EXACT.first_mismatch("1.0\n", "1\n").is_some());
+        assert!(Comparator { error: Some(0.0) }
+            .first_mismatch("1.0\n", "1\n")
+            .is_none());
+    }
+
+    #[test]
+    fn inf_and_nan_are_words() {
+        let comparator = Comparator { error: Some(1e-6) };
+        assert!(comparator.first_mismatch("inf\n", "inf\n").is_none());
+        assert!(comparator.first_mismatch("inf\n", "infinity\n").is_some());
+        assert!(comparator.first_mismatch("NaN\n", "nan\n").is_some());
+        assert!(comparator.first_mismatch("1e400\n", "inf\n").is_some());
+        // Words are never within the error of a number.
+        assert!(comparator.first_mismatch("nan\n", "0\n").is_some());
+    }
+
+    #[test]
+    fn token_span_uses_byte_offsets() {
+        assert_eq!(token_span("ab  cd", 1), Some((4, 6)));
+        assert_eq!(token_span("  ab", 0), Some((2, 4)));
+        assert_eq!(token_span("ab", 1), None);
+        let text = "はい\u{3000}いいえ 10";
+        // The ideographic space separates tokens as well.
+        let (from, to) = token_span(text, 1).unwrap();
+        assert_eq!(&text[from..to], "いいえ");
+        let (from, to) = token_span(text, 2).unwrap();
+        assert_eq!(&text[from..to], "10");
+    }
+}
@@ -0,0 +1,269 @@
+//! Running interactive problems against a local judge program.
+//!
+//! The judge is invoked as `judge [<input-file>]`; its stdout is fed to the solution's
+//! stdin and vice versa. The judge decides the verdict by its exit status (0 = AC) and
+//! may explain itself on stderr.
+
+use anyhow::{bail, Context, Result};
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    path::Path,
+    process::{Command, ExitStatus, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::runner::{
+    describe_failure, drain, format_memory, isolate, kill_process_tree, wait_with_limit,
+};
+use crate::Verdict;
+
+/// How long a rejected solution may take to exit on its own before it is killed.
+const SETTLE_TIME: Duration = Duration::from_millis(100);
+
+/// Extra time the judge gets after the solution's time limit to deliver its verdict.
+const JUDGE_GRACE: Duration = Duration::from_secs(5);
+
+/// Result of one interactive session.
+pub struct Session {
+    pub verdict: Verdict,
+    pub elapsed: Duration,
+    pub peak_memory: Option<u64>,
+    pub judge_message: String,
+    pub solution_stderr: String,
+    pub solution_status: ExitStatus,
+}
+
+/// Run `solution` against `judge` once, printing the transcript as it happens.
+pub fn run_session(
+    dir: &Path,
+    solution: &Path,
+    judge: &Path,
+    judge_input: Option<&Path>,
+    time_limit: Duration,
+) -> Result<Session> {
+    let mut judge_cmd = Command::new(judge);
+    judge_cmd
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(input) = judge_input {
+        judge_cmd.arg(input);
+    }
+    isolate(&mut judge_cmd);
+
+    let mut solution_cmd = Command::new(solution);
+    solution_cmd
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    isolate(&mut solution_cmd);
+
+    let start = Instant::now();
+    let mut judge_child = judge_cmd
+        .spawn()
+        .with_context(|| format!("Failed to spawn judge {}", judge.display()))?;
+    let mut solution_child = match solution_cmd.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            kill_process_tree(judge_child.id());
+            let _ = judge_child.wait();
+            bail!("Failed to spawn {}: {}", solution.display(), e);
+        }
+    };
+
+    let to_judge = relay(
+        solution_child.stdout.take().expect("Failed to open stdout"),
+        judge_child.stdin.take().expect("Failed to open stdin"),
+        "sol  ",
+        start,
+    );
+    let to_solution = relay(
+        judge_child.stdout.take().expect("Failed to open stdout"),
+        solution_child.stdin.take().expect("Failed to open stdin"),
+        "judge",
+        start,
+    );
+    let judge_stderr = drain(judge_child.stderr.take().expect("Failed to open stderr"));
+    let solution_stderr = drain(solution_child.stderr.take().expect("Failed to open stderr"));
+
+    let solution_pid = solution_child.id();
+    let solution_waiter = thread::spawn(move || {
+        let result = wait_with_limit(&mut solution_child, start, Some(time_limit));
+        (result, start.elapsed())
+    });
+
+    let (judge_status, _, judge_timed_out) =
+        wait_with_limit(&mut judge_child, start, Some(time_limit + JUDGE_GRACE))?;
+    let mut killed = false;
+    if !judge_status.success() {
+        // The judge has made up its mind; don't let the solution wait for more input.
+        // A solution that crashed may still be on its way out, so give it a moment
+        // before deciding it was us who stopped it.
+        let deadline = Instant::now() + SETTLE_TIME;
+        while !solution_waiter.is_finished() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(1));
+        }
+        if !solution_waiter.is_finished() {
+            kill_process_tree(solution_pid);
+            killed = true;
+        }
+    }
+    let (solution_result, elapsed) = solution_waiter
+        .join()
+        .map_err(|_| anyhow::anyhow!("solution waiter panicked"))?;
+    let (solution_status, peak_memory, timed_out) = solution_result?;
+    let _ = to_judge.join();
+    let _ = to_solution.join();
+    let judge_message = String::from_utf8_lossy(&judge_stderr.join().unwrap_or_default())
+        .trim_end()
+        .to_string();
+    let solution_stderr =
+        String::from_utf8_lossy(&solution_stderr.join().unwrap_or_default()).to_string();
+
+    if judge_timed_out && !timed_out {
+        bail!("judge did not finish within {:?}", time_limit + JUDGE_GRACE);
+    }
+    // A solution finishing just past the limit may not have been caught by the poll.
+    let verdict = if timed_out || elapsed > time_limit {
+        Verdict::Tle
+    } else if !solution_status.success() && !killed {
+        Verdict::Re
+    } else if !judge_status.success() {
+        Verdict::Wa
+    } else {
+        Verdict::Ac
+    };
+    Ok(Session {
+        verdict,
+        elapsed,
+        peak_memory,
+        judge_message,
+        solution_stderr,
+        solution_status,
+    })
+}
+
+impl Session {
+    /// Why the solution failed, when it did not exit normally.
+    pub fn failure_reason(&self) -> Option<String> {
+        describe_failure(&self.solution_status, &self.solution_stderr)
+    }
+
+    /// One-line summary of time and memory.
+    pub fn stats(&self) -> String {
+        match self.peak_memory {
+            Some(memory) => format!("{} ms  {}", self.elapsed.as_millis(), format_memory(memory)),
+            None => format!("{} ms", self.elapsed.as_millis()),
+        }
+    }
+}
+
+/// Forward `from` to `to` line by line on a background thread, logging every line.
+///
+/// `to` is closed once `from` reaches end of file so the other side sees EOF.
+fn relay(
+    from: impl Read + Send + 'static,
+    mut to: impl Write + Send + 'static,
+    label: &'static str,
+    start: Instant,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(from);
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+            let text = String::from_utf8_lossy(&line);
+            println!(
+                "[{:>10.3} ms] {} > {}",
+                start.elapsed().as_secs_f64() * 1000.0,
+                label,
+                text.trim_end_matches(['\r', '\n'])
+            );
+            if to.write_all(&line).and_then(|_| to.flush()).is_err() {
+                break;
+            }
+        }
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::{fs, os::unix::fs::PermissionsExt, path::PathBuf};
+
+    /// Write the shell script `body` as an executable `name` in the scratch directory `dir`.
+    fn script(dir: &Path, name: &str, body: &str) -> PathBuf {
+        fs::create_dir_all(dir).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, format!("#!/bin/sh\n{body}\n")).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    /// Run the solution `body` against a judge that sends the number in its input file
+    /// and wants it doubled back.
+    fn session(name: &str, body: &str, time_limit: Duration) -> Session {
+        let dir = std::env::temp_dir().join(format!("kp-test-{}-{name}", std::process::id()));
+        let judge = script(
+            &dir,
+            "judge.sh",
+            "n=$(cat \"$1\")\n\
+             echo \"$n\"\n\
+             read -r answer || { echo 'no answer' >&2; exit 1; }\n\
+             if [ \"$answer\" = $((n * 2)) ]; then exit 0; fi\n\
+             echo \"expected $((n * 2)), got $answer\" >&2\n\
+             exit 1",
+        );
+        let solution = script(&dir, "solution.sh", body);
+        let input = dir.join("1.in");
+        fs::write(&input, "21\n").unwrap();
+        let session = run_session(&dir, &solution, &judge, Some(&input), time_limit).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        session
+    }
+
+    const TIME_LIMIT: Duration = Duration::from_secs(5);
+
+    #[test]
+    fn accepted() {
+        let session = session("interactive-ac", "read -r n\necho $((n * 2))", TIME_LIMIT);
+        assert!(matches!(session.verdict, Verdict::Ac));
+        assert_eq!(session.judge_message, "");
+        assert!(session.failure_reason().is_none());
+    }
+
+    #[test]
+    fn wrong_answer() {
+        let session = session("interactive-wa", "read -r n\necho $((n + 1))", TIME_LIMIT);
+        assert!(matches!(session.verdict, Verdict::Wa));
+        assert_eq!(session.judge_message, "expected 42, got 22");
+    }
+
+    #[test]
+    fn time_limit_exceeded() {
+        let start = Instant::now();
+        let session = session(
+            "interactive-tle",
+            "read -r n\nsleep 10",
+            Duration::from_millis(200),
+        );
+        assert!(matches!(session.verdict, Verdict::Tle));
+        // Killing the solution lets the judge finish right away.
+        assert!(start.elapsed() < TIME_LIMIT);
+        assert_eq!(session.judge_message, "no answer");
+    }
+
+    #[test]
+    fn runtime_error() {
+        let session = session("interactive-re", "read -r n\nexit 3", TIME_LIMIT);
+        assert!(matches!(session.verdict, Verdict::Re));
+        assert_eq!(session.failure_reason().as_deref(), Some("exit code 3"));
+    }
+}
@@ -1,22 +1,55 @@
 // kp: AtCoder project management CLI
 // ------------------------------------------------------------
-// * kp new <contest_id>      : generate contest workspace
-// * kp test <contest_id> <problem> : build & `oj test` a single task
+// * kp new <contest_id>      : download a contest and generate its workspace
+// * kp test <contest_id> [problem] : build & judge a task (or every task) against its samples
+// * kp interactive <contest_id> <problem> : run an interactive task against a local judge
+// * kp stress <contest_id> <problem> : compare a task with a brute force on random inputs
+// * kp watch <contest_id> <problem> : re-run the samples whenever the task changes
+// * kp import <contest_id> <problem> <page.html> : take the samples from a saved task page
+// * kp run <contest_id> <problem> : run a task on stdin (or a file) and report time/memory
+// * kp bundle <contest_id> <problem> : inline the used local libraries into submit/<problem>.rs
+// * kp submit <contest_id> <problem> : test, bundle and submit a task
+// * kp case add|list|show|rm <contest_id> <problem> : manage hand-made test cases
+// * kp check <contest_id> [problem] : warn about crates the judge does not have
+// * kp login | logout | whoami : manage the saved AtCoder session
+//
+// Inside a contest workspace the contest (and the problem) may be omitted.
 // ------------------------------------------------------------
 
 use anyhow::{bail, Context, Result};
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use serde::Deserialize;
 use serde_json::{json, Value};
+use std::ffi::OsStr;
+use std::fmt;
 use std::{
     fs::{self, File},
-    io::{BufReader, Write},
+    io::{BufReader, IsTerminal, Read, Write},
     path::{Path, PathBuf},
-    process::{exit, Command},
+    process::{exit, Command, Stdio},
+    time::Duration,
 };
 use toml_edit::{ArrayOfTables, DocumentMut, Item, Table};
-use toml_edit::Document;
-use std::ffi::OsStr;
+
+mod atcoder;
+mod bundle;
+mod check;
+mod checker;
+mod compare;
+mod interactive;
+mod runner;
+mod session;
+mod shrink;
+mod template;
+
+use checker::Checker;
+use compare::{use_color, Comparator};
+use runner::{
+    build_bin, build_bins, ensure_sibling_bin, format_memory, parse_duration, parse_memory,
+    resolve_problem_bin, run_binary, run_binary_live, run_binary_with_args, run_ordered, Execution,
+};
+use shrink::{Described, FreeForm, InputFormat};
+use template::{Template, TEMPLATE_NAME};
 
 #[derive(Parser)]
 #[command(author, version, about)]
@@ -33,36 +66,305 @@ enum Cmd {
     New {
         /// Contest ID (e.g. abc300)
         contest: String,
+        /// Where to download from, e.g. a local server with saved pages
+        #[arg(long, env = "KP_BASE_URL", default_value = atcoder::DEFAULT_BASE_URL)]
+        base_url: String,
     },
-    /// Build & `oj test` a problem
+    /// Build & judge a problem against its sample cases (every problem when omitted)
     Test {
-        /// Contest ID (e.g. abc300)
-        contest: String,
-        /// Problem ID letter (e.g. a)
-        problem: String,
+        #[command(flatten)]
+        target: TargetArgs,
+        /// Test every problem of the contest
+        #[arg(long, conflicts_with = "problem")]
+        all: bool,
+        /// Number of test cases run at once (0 = one per CPU core); 1 keeps timings precise
+        #[arg(short, long, default_value_t = 1)]
+        jobs: usize,
+        #[command(flatten)]
+        judge: JudgeArgs,
     },
     /// Debug a problem (show input/output/expect/comparison)
     Debug {
-        /// Contest ID (e.g. abc300)
-        contest: String,
-        /// Problem ID letter (e.g. a)
-        problem: String,
+        #[command(flatten)]
+        target: TargetArgs,
+        /// Number of test cases run at once (0 = one per CPU core); 1 keeps timings precise
+        #[arg(short, long, default_value_t = 1)]
+        jobs: usize,
+        #[command(flatten)]
+        judge: JudgeArgs,
+    },
+    /// Re-run the samples of a problem whenever its source or test cases change
+    Watch {
+        #[command(flatten)]
+        target: TargetArgs,
+        /// Number of test cases run at once (0 = one per CPU core); 1 keeps timings precise
+        #[arg(short, long, default_value_t = 1)]
+        jobs: usize,
+        #[command(flatten)]
+        judge: JudgeArgs,
+    },
+    /// Run an interactive problem against a local judge program
+    Interactive {
+        #[command(flatten)]
+        target: TargetArgs,
+        /// Judge program: a bin name in the contest or a path to an executable
+        /// (defaults to testcases/<problem>/judge.rs)
+        #[arg(long, value_name = "BIN|PATH")]
+        judge: Option<String>,
+        /// Time limit per session (e.g. 2s, 500ms)
+        #[arg(long, value_parser = parse_duration)]
+        tl: Option<Duration>,
+    },
+    /// Compare a problem's solution with a brute-force one on generated inputs
+    Stress {
+        #[command(flatten)]
+        target: TargetArgs,
+        /// Generator bin, run as `<gen> <seed>`; it must print one input
+        #[arg(long)]
+        gen: String,
+        /// Brute-force solution bin whose output is taken as the expected one
+        #[arg(long)]
+        naive: String,
+        /// Number of generated inputs to try
+        #[arg(long, short = 'n', default_value_t = 1000)]
+        iterations: u64,
+        /// Seed of the first input (defaults to one derived from the clock)
+        #[arg(long)]
+        seed: Option<u64>,
+        /// Input format description used to shrink a failing input
+        /// (defaults to testcases/<problem>/format.txt when present)
+        #[arg(long, value_name = "FILE")]
+        format: Option<PathBuf>,
+        /// Save the failing input as found, without shrinking it
+        #[arg(long)]
+        no_minimize: bool,
+        #[command(flatten)]
+        judge: JudgeArgs,
+    },
+    /// Extract the samples of a saved task page into the problem's test cases
+    Import {
+        /// Contest and problem as for the other commands, followed by the HTML file
+        #[arg(num_args = 1..=3, required = true, value_name = "[CONTEST] [PROBLEM] PAGE")]
+        args: Vec<String>,
+    },
+    /// Run a problem on an input typed in (or read from a file) and report time and memory
+    Run {
+        #[command(flatten)]
+        target: TargetArgs,
+        /// Read the input from this file instead of stdin
+        #[arg(long, short, value_name = "FILE")]
+        input: Option<PathBuf>,
+    },
+    /// Inline the local libraries a problem uses into one file, submit/<problem>.rs
+    Bundle {
+        #[command(flatten)]
+        target: TargetArgs,
     },
+    /// Test a problem, bundle it and submit it (needs `kp login`)
+    Submit {
+        #[command(flatten)]
+        target: TargetArgs,
+        /// Submit even if a sample fails
+        #[arg(long)]
+        force: bool,
+        /// Language ID of the submit form (defaults to the Rust one offered)
+        #[arg(long)]
+        language_id: Option<String>,
+        /// Return right after submitting instead of waiting for the verdict
+        #[arg(long)]
+        no_wait: bool,
+        /// Where to submit, e.g. a local mock server
+        #[arg(long, env = "KP_BASE_URL", default_value = atcoder::DEFAULT_BASE_URL)]
+        base_url: String,
+        #[command(flatten)]
+        judge: JudgeArgs,
+    },
+    /// Manage the test cases of a problem
+    Case {
+        #[command(subcommand)]
+        cmd: CaseCmd,
+    },
+    /// Warn about dependencies and `use`d crates that the judge does not have
+    Check {
+        #[command(flatten)]
+        target: TargetArgs,
+    },
+    /// Log in to AtCoder and save the session in the kp config directory
+    Login {
+        /// User name (asked for when omitted); the password is read from the terminal
+        #[arg(long, short)]
+        username: Option<String>,
+        #[arg(long, env = "KP_BASE_URL", default_value = atcoder::DEFAULT_BASE_URL)]
+        base_url: String,
+    },
+    /// Forget the saved session
+    Logout {
+        #[arg(long, env = "KP_BASE_URL", default_value = atcoder::DEFAULT_BASE_URL)]
+        base_url: String,
+    },
+    /// Show who the session is logged in as, failing if it has expired
+    Whoami {
+        #[arg(long, env = "KP_BASE_URL", default_value = atcoder::DEFAULT_BASE_URL)]
+        base_url: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum CaseCmd {
+    /// Add a hand-made case, reading the input from stdin (or $EDITOR when it is a terminal)
+    Add {
+        #[command(flatten)]
+        target: TargetArgs,
+        /// Bin (or sibling `<name>.rs`) run on the input to produce the expected output
+        #[arg(long, conflicts_with = "expect")]
+        reference: Option<String>,
+        /// File holding the expected output (otherwise it is asked for in $EDITOR)
+        #[arg(long, value_name = "FILE")]
+        expect: Option<PathBuf>,
+        /// Write the input in $EDITOR even if stdin is not a terminal
+        #[arg(long)]
+        edit: bool,
+    },
+    /// List the test cases of a problem
+    List {
+        #[command(flatten)]
+        target: TargetArgs,
+    },
+    /// Print the input and expected output of a case
+    Show {
+        /// Case name (e.g. custom-1, or just 1 for custom-1)
+        name: String,
+        #[command(flatten)]
+        target: TargetArgs,
+    },
+    /// Delete a case
+    Rm {
+        /// Case name (e.g. custom-1, or just 1 for custom-1)
+        name: String,
+        #[command(flatten)]
+        target: TargetArgs,
+        /// Also allow deleting downloaded samples
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+/// The contest and problem a command works on.
+///
+/// Both may be omitted inside a contest workspace: the contest is the nearest directory
+/// (upwards) holding `contest.acc.json`, and the problem is the task whose source was
+/// edited last. A single argument naming a task of that workspace is taken as the problem.
+#[derive(Args)]
+struct TargetArgs {
+    /// Contest ID or directory (e.g. abc300)
+    contest: Option<String>,
+    /// Problem ID letter (e.g. a)
+    problem: Option<String>,
 }
+
+/// Options controlling how a solution is judged.
+#[derive(Args, Default)]
+struct JudgeArgs {
+    /// Time limit per test case (e.g. 2s, 500ms)
+    #[arg(long, value_parser = parse_duration)]
+    tl: Option<Duration>,
+    /// Memory limit per test case (e.g. 1024MiB)
+    #[arg(long, value_parser = parse_memory)]
+    ml: Option<u64>,
+    /// Accept numeric tokens within this absolute or relative error (e.g. 1e-6)
+    #[arg(long)]
+    error: Option<f64>,
+    /// Special judge: a bin name in the contest or a path to a testlib-style checker
+    /// (defaults to testcases/<problem>/checker.rs when present)
+    #[arg(long, value_name = "BIN|PATH")]
+    checker: Option<String>,
+}
+
+impl JudgeArgs {
+    /// `--tl`, else the task's own limit, else the default.
+    fn time_limit(&self, task: Option<&Task>) -> Duration {
+        self.tl
+            .or_else(|| task?.time_limit())
+            .unwrap_or(DEFAULT_TIME_LIMIT)
+    }
+
+    /// `--ml`, else the task's own limit, else the default.
+    fn memory_limit(&self, task: Option<&Task>) -> u64 {
+        self.ml
+            .or_else(|| task?.memory_limit())
+            .unwrap_or(DEFAULT_MEMORY_LIMIT)
+    }
+
+    fn comparator(&self) -> Comparator {
+        Comparator { error: self.error }
+    }
+}
+
 #[derive(Deserialize)]
 struct Input {
+    contest: Option<Contest>,
     tasks: Vec<Task>,
 }
 
+#[derive(Deserialize)]
+struct Contest {
+    /// e.g. "abc300"
+    id: String,
+}
+
 #[derive(Deserialize)]
 struct Task {
     /// e.g. "A", "B", …
     label: String,
     directory: Directory,
+    /// e.g. "abc300_a"
+    id: Option<String>,
+    // Written by `kp new`; absent from workspaces made by atcoder-cli.
+    title: Option<String>,
+    /// Page of the task on the site.
+    url: Option<String>,
+    time_limit_ms: Option<u64>,
+    memory_limit_mb: Option<u64>,
+    score: Option<u64>,
+}
+
+impl Task {
+    fn time_limit(&self) -> Option<Duration> {
+        self.time_limit_ms.map(Duration::from_millis)
+    }
+
+    /// In bytes.
+    fn memory_limit(&self) -> Option<u64> {
+        self.memory_limit_mb.map(|mb| mb << 20)
+    }
+
+    /// One-line description, e.g. `A - Sum (2000 ms, 1024.0 MiB, 100 points)`.
+    fn describe(&self) -> String {
+        let mut out = self.label.clone();
+        if let Some(title) = &self.title {
+            out += &format!(" - {title}");
+        }
+        let mut details = Vec::new();
+        if let Some(tl) = self.time_limit() {
+            details.push(format!("{} ms", tl.as_millis()));
+        }
+        if let Some(ml) = self.memory_limit() {
+            details.push(format_memory(ml));
+        }
+        if let Some(score) = self.score {
+            details.push(format!("{score} points"));
+        }
+        if !details.is_empty() {
+            out += &format!(" ({})", details.join(", "));
+        }
+        out
+    }
 }
 
 #[derive(Deserialize)]
 struct Directory {
+    /// Task directory relative to the contest, e.g. "./"
+    path: Option<String>,
     /// e.g. "a.rs"
     submit: String,
 }
@@ -76,10 +378,260 @@ fn main() {
 fn run() -> Result<()> {
     match Cli::parse().cmd {
         Cmd::Init {} => init_template(),
-        Cmd::New { contest } => create_contest(&contest),
-        Cmd::Test { contest, problem } => test_problem(&contest, &problem),
-        Cmd::Debug { contest, problem } => debug_problem(&contest, &problem),
+        Cmd::New { contest, base_url } => create_contest(&contest, &base_url),
+        Cmd::Test {
+            target,
+            all,
+            jobs,
+            judge,
+        } => {
+            // A contest named without a problem means the whole contest.
+            match target.locate()? {
+                (dir, _) if all => test_contest(&dir, jobs, &judge),
+                (dir, Some(problem)) => test_problem(&dir, &problem, jobs, &judge),
+                (dir, None) if target.contest.is_some() => test_contest(&dir, jobs, &judge),
+                _ => {
+                    let (dir, problem) = target.resolve()?;
+                    test_problem(&dir, &problem, jobs, &judge)
+                }
+            }
+        }
+        Cmd::Debug {
+            target,
+            jobs,
+            judge,
+        } => {
+            let (dir, problem) = target.resolve()?;
+            debug_problem(&dir, &problem, jobs, &judge)
+        }
+        Cmd::Watch {
+            target,
+            jobs,
+            judge,
+        } => {
+            let (dir, problem) = target.resolve()?;
+            watch_problem(&dir, &problem, jobs, &judge)
+        }
+        Cmd::Stress {
+            target,
+            gen,
+            naive,
+            iterations,
+            seed,
+            format,
+            no_minimize,
+            judge,
+        } => {
+            let (dir, problem) = target.resolve()?;
+            let options = StressOptions {
+                gen,
+                naive,
+                iterations,
+                seed,
+                format,
+                minimize: !no_minimize,
+            };
+            stress_problem(&dir, &problem, &options, &judge)
+        }
+        Cmd::Import { mut args } => {
+            let page = PathBuf::from(args.pop().expect("at least one argument"));
+            let mut args = args.into_iter();
+            let target = TargetArgs {
+                contest: args.next(),
+                problem: args.next(),
+            };
+            let (dir, problem) = target.resolve()?;
+            import_samples(&dir, &problem, &page)
+        }
+        Cmd::Run { target, input } => {
+            let (dir, problem) = target.resolve()?;
+            run_problem(&dir, &problem, input.as_deref())
+        }
+        Cmd::Bundle { target } => {
+            let (dir, problem) = target.resolve()?;
+            bundle_problem(&dir, &problem).map(|_| ())
+        }
+        Cmd::Submit {
+            target,
+            force,
+            language_id,
+            no_wait,
+            base_url,
+            judge,
+        } => {
+            let (dir, problem) = target.resolve()?;
+            let options = SubmitOptions {
+                force,
+                language_id,
+                wait: !no_wait,
+                base_url,
+            };
+            submit_problem(&dir, &problem, &options, &judge).map(|_| ())
+        }
+        Cmd::Check { target } => {
+            let (dir, problem) = target.locate()?;
+            check_contest(&dir, problem.as_deref())
+        }
+        Cmd::Login { username, base_url } => login(&base_url, username),
+        Cmd::Logout { base_url } => logout(&base_url),
+        Cmd::Whoami { base_url } => whoami(&base_url),
+        Cmd::Case { cmd } => match cmd {
+            CaseCmd::Add {
+                target,
+                reference,
+                expect,
+                edit,
+            } => {
+                let (dir, problem) = target.resolve()?;
+                add_case(
+                    &dir,
+                    &problem,
+                    reference.as_deref(),
+                    expect.as_deref(),
+                    edit,
+                )
+            }
+            CaseCmd::List { target } => {
+                let (dir, problem) = target.resolve()?;
+                list_cases(&dir, &problem)
+            }
+            CaseCmd::Show { name, target } => {
+                let (dir, problem) = target.resolve()?;
+                show_case(&dir, &problem, &name)
+            }
+            CaseCmd::Rm {
+                name,
+                target,
+                force,
+            } => {
+                let (dir, problem) = target.resolve()?;
+                remove_case(&dir, &problem, &name, force)
+            }
+        },
+        Cmd::Interactive { target, judge, tl } => {
+            let (dir, problem) = target.resolve()?;
+            interactive_problem(
+                &dir,
+                &problem,
+                judge.as_deref(),
+                tl.or_else(|| find_task(&dir, &problem)?.time_limit())
+                    .unwrap_or(DEFAULT_TIME_LIMIT),
+            )
+        }
+    }
+}
+
+impl TargetArgs {
+    /// Contest directory and problem, inferring whatever was not given.
+    fn resolve(&self) -> Result<(PathBuf, String)> {
+        let cwd = std::env::current_dir()?;
+        let (dir, problem) = self.locate_from(&cwd)?;
+        let problem = match problem {
+            Some(problem) => problem,
+            None => {
+                let problem = infer_problem(&dir, &cwd)?;
+                println!("🎯  {} {}", dir.display(), problem);
+                problem
+            }
+        };
+        Ok((dir, problem))
+    }
+
+    /// Contest directory and the problem named on the command line, if any.
+    fn locate(&self) -> Result<(PathBuf, Option<String>)> {
+        self.locate_from(&std::env::current_dir()?)
+    }
+
+    /// [`TargetArgs::locate`], as seen from the directory `cwd`.
+    fn locate_from(&self, cwd: &Path) -> Result<(PathBuf, Option<String>)> {
+        let (dir, problem) = match (&self.contest, &self.problem) {
+            (Some(contest), Some(problem)) => (contest_dir(cwd, contest), Some(problem.clone())),
+            (Some(arg), None) => match find_workspace(cwd) {
+                Some(workspace) if is_problem_of(&workspace, arg) => (workspace, Some(arg.clone())),
+                _ => (contest_dir(cwd, arg), None),
+            },
+            (None, _) => match find_workspace(cwd) {
+                Some(workspace) => (workspace, None),
+                None => bail!(
+                    "not inside a contest workspace (no contest.acc.json found); \
+                     pass the contest ID"
+                ),
+            },
+        };
+        if !cwd.join(&dir).exists() {
+            bail!("{} does not exist", dir.display());
+        }
+        Ok((dir, problem.map(|p| p.to_lowercase())))
+    }
+}
+
+/// The nearest directory, from `cwd` upwards, that holds `contest.acc.json`.
+fn find_workspace(cwd: &Path) -> Option<PathBuf> {
+    cwd.ancestors()
+        .find(|dir| dir.join("contest.acc.json").is_file())
+        .map(Path::to_path_buf)
+}
+
+/// Directory of the contest named `contest` on the command line: the workspace enclosing
+/// `cwd` when its contest ID or directory name is `contest`, else `contest` as a path.
+fn contest_dir(cwd: &Path, contest: &str) -> PathBuf {
+    if let Some(workspace) = find_workspace(cwd) {
+        let id = read_contest_json(&workspace)
+            .ok()
+            .and_then(|input| Some(input.contest?.id));
+        let name = workspace
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned());
+        if [id, name]
+            .into_iter()
+            .flatten()
+            .any(|n| n.eq_ignore_ascii_case(contest))
+        {
+            return workspace;
+        }
     }
+    PathBuf::from(contest)
+}
+
+/// Whether `name` is a task (or bin) of the contest in `dir`.
+fn is_problem_of(dir: &Path, name: &str) -> bool {
+    let is_task = read_contest_json(dir)
+        .map(|input| {
+            input
+                .tasks
+                .iter()
+                .any(|t| t.label.eq_ignore_ascii_case(name))
+        })
+        .unwrap_or(false);
+    is_task || matches!(manifest_bin_path(dir, &name.to_lowercase()), Ok(Some(_)))
+}
+
+/// Guess the problem being worked on in the contest at `dir`.
+///
+/// A current directory `cwd` inside a task's own directory selects that task; otherwise
+/// the task whose submit file was modified last wins.
+fn infer_problem(dir: &Path, cwd: &Path) -> Result<String> {
+    let input = read_contest_json(dir)?;
+    let root = fs::canonicalize(dir)?;
+    for task in &input.tasks {
+        let Some(path) = task.directory.path.as_deref() else {
+            continue;
+        };
+        let task_dir = root.join(path);
+        if task_dir != root && fs::canonicalize(&task_dir).is_ok_and(|d| cwd.starts_with(d)) {
+            return Ok(task.label.to_lowercase());
+        }
+    }
+    input
+        .tasks
+        .iter()
+        .max_by_key(|task| {
+            fs::metadata(dir.join(&task.directory.submit))
+                .and_then(|m| m.modified())
+                .ok()
+        })
+        .map(|task| task.label.to_lowercase())
+        .ok_or_else(|| anyhow::anyhow!("contest.acc.json lists no tasks"))
 }
 
 //
@@ -96,28 +648,13 @@ fn command(command_str: &str) -> Command {
 }
 /// `kp init`
 fn init_template() -> Result<()> {
-    // 1. Obtain the path printed by `npx atcoder-cli config-dir`
-    let output = command("npx atcoder-cli")
-        .arg("config-dir")
-        .output()
-        .context("failed to start `npx atcoder-cli config-dir`")?;
-
-    if !output.status.success() {
-        return Err(anyhow::anyhow!(
-            "`npx atcoder-cli config-dir` exited with status {}",
-            output.status
-        ));
-    }
-    let config_dir = String::from_utf8(output.stdout)
-        .context("`npx atcoder-cli config-dir` produced non-UTF-8 output")?
-        .trim()
-        .replace("\r\n", "")
-        .replace('\n', "");
-    // Remove trailing new-line(s) and convert to PathBuf
-    let config_dir = PathBuf::from(config_dir.trim());
+    // 1. The template lives in kp's own config directory
+    let config_dir = config_dir()
+        .context("cannot locate the config directory (neither HOME nor APPDATA is set)")?;
+    fs::create_dir_all(&config_dir)?;
 
     // 2. Decide whether `kp-rust` exists
-    let kp_path = config_dir.join("kp-rust");
+    let kp_path = config_dir.join(TEMPLATE_NAME);
 
     if kp_path.exists() {
         // 3-a. Pull the latest changes
@@ -144,99 +681,91 @@ fn init_template() -> Result<()> {
         }
     }
 
-    // 4. Set Config the template
-    let default_template = command("npx atcoder-cli")
-        .arg("config")
-        .arg("default-template")
-        .output()
-        .context("failed to run `npx atcoder-cli config default-template`")?;
-
-    let status = default_template.status;
-    if !status.success() {
-        return Err(anyhow::anyhow!(
-            "`npx atcoder-cli config default-template` failed with status {}",
-            status
-        ));
-    }
-    let current_template = String::from_utf8(default_template.stdout)
-        .context("`npx atcoder-cli config default-template` produced non-UTF-8 output")?;
-    if current_template.trim() != "kp-rust" {
-        // npx atcoder-cli config default-template kp-rust
-        let set_template = command("npx atcoder-cli")
-            .args(["config", "default-template", "kp-rust"])
-            .status()
-            .context("failed to run `npx atcoder-cli config default-template kp-rust`")?;
-        if !set_template.success() {
-            return Err(anyhow::anyhow!(
-                "`npx atcoder-cli config default-template kp-rust` failed with status {}",
-                set_template
-            ));
-        }
-    }
-    command("npx atcoder-cli")
-        .args(["config", "default-task-dirname-format", "./"])
-        .status()
-        .context("failed to run `npx atcoder-cli config default-task-dirname-format ./`")?;
-
-    command("npx atcoder-cli")
-        .args(["config", "default-task-choice", "all"])
-        .status()
-        .context("failed to run `npx atcoder-cli config default-task-choice all`")?;
-
+    println!("Template installed in {}", kp_path.display());
     Ok(())
 }
 
 /// `kp new`
-fn create_contest(contest: &str) -> Result<()> {
+fn create_contest(contest: &str, base_url: &str) -> Result<()> {
     let root = Path::new(contest);
     if root.exists() {
         bail!("Directory {contest} already exists");
     }
-    // Remove directories
-    // Create the contest directory
-    command("npx atcoder-cli")
-        .args(["new", contest])
-        .status()
-        .context(format!("failed to run `npx atcoder-cli new {}`", contest))?;
-
-    // -------- 0. get directory argument --------
-    let json_path = Path::new(contest).join("contest.acc.json");
-
-    // -------- 1. read JSON --------
-    let file =
-        fs::File::open(&json_path).with_context(|| format!("cannot open {:?}", json_path))?;
-    let input: Input = serde_json::from_reader(file)?;
 
-    // -------- 2. load Cargo.toml (project root) --------
-    let cargo_path = Path::new(contest).join("Cargo.toml");
-    let mut doc = fs::read_to_string(&cargo_path)?.parse::<DocumentMut>()?;
+    // -------- 1. download tasks and samples --------
+    let client = atcoder_client(base_url)?;
+    let (title, tasks) = client.contest(contest)?;
+    println!("📥  {title} ({} tasks)", tasks.len());
+    let mut pages = Vec::new();
+    for task in &tasks {
+        let page = client
+            .task_page(task)
+            .with_context(|| format!("Failed to fetch task {}", task.label))?;
+        let time_limit = task
+            .time_limit
+            .map(|tl| format!("{} s", tl.as_secs_f64()))
+            .unwrap_or_else(|| "?".to_string());
+        let memory_limit = task
+            .memory_limit
+            .map(format_memory)
+            .unwrap_or_else(|| "?".to_string());
+        let score = page.score.map_or("?".to_string(), |s| s.to_string());
+        println!(
+            "  {:<3} {}  ({}, {}, {} points, {} samples)",
+            task.label,
+            task.title,
+            time_limit,
+            memory_limit,
+            score,
+            page.samples.len()
+        );
+        pages.push(page);
+    }
 
-    // ① Ensure [[bin]] is an ArrayOfTables, not a Value::Array
-    if doc.get("bin").is_none() {
-        doc["bin"] = Item::ArrayOfTables(ArrayOfTables::new());
+    // -------- 2. generate the workspace --------
+    let template = Template::find()?;
+    println!("📁  creating {contest} from {}", template.describe());
+    template.create(root, contest, &tasks)?;
+    let mut task_entries = Vec::new();
+    for (index, (task, page)) in tasks.iter().zip(&pages).enumerate() {
+        let testdir = format!("testcases/{}", task.label.to_lowercase());
+        write_samples(&root.join(&testdir), &page.samples)?;
+        task_entries.push(json!({
+            "id": task.id,
+            "label": task.label,
+            "title": task.title,
+            "url": task.url,
+            "time_limit_ms": task.time_limit.map(|tl| tl.as_millis() as u64),
+            "memory_limit_mb": task.memory_limit.map(|ml| ml >> 20),
+            "score": page.score,
+            "directory": {
+                "path": "./",
+                "testdir": testdir,
+                "submit": template.submit(contest, index, task),
+            },
+        }));
     }
-    let bins = doc["bin"]
-        .as_array_of_tables_mut() // ✅ correct accessor
-        .expect("`bin` must be an array-of-tables");
+    let contest_json = json!({
+        "contest": {
+            "id": contest,
+            "title": title,
+            "url": client.url(&format!("/contests/{contest}")),
+        },
+        "tasks": task_entries,
+    });
+    fs::write(
+        root.join("contest.acc.json"),
+        serde_json::to_string_pretty(&contest_json)?,
+    )?;
 
-    for task in input.tasks {
-        let name = task.label.to_lowercase();
-        let path = format!("{}", task.directory.submit);
+    // -------- 3. register the tasks as bins --------
+    let input = read_contest_json(root)?;
 
-        // ② Each element is &Table, so we can inspect keys normally
-        if bins
-            .iter()
-            .any(|tbl: &Table| tbl.get("name").and_then(|v| v.as_str()) == Some(name.as_str()))
-        {
-            continue; // already present
-        }
+    let cargo_path = root.join("Cargo.toml");
+    let mut doc = fs::read_to_string(&cargo_path)?.parse::<DocumentMut>()?;
 
-        // ③ Push a new table
-        let mut t = Table::new();
-        t["name"] = name.clone().into();
-        t["path"] = path.into();
-        t.set_implicit(true); // no '{}' braces
-        bins.push(t);
+    for task in input.tasks {
+        add_bin(&mut doc, &task.label.to_lowercase(), &task.directory.submit)?;
     }
 
     fs::write(&cargo_path, doc.to_string())?;
@@ -295,44 +824,1309 @@ fn create_contest(contest: &str) -> Result<()> {
     Ok(())
 }
 
-/// `kp test`
-fn test_problem(contest: &str, problem: &str) -> Result<()> {
-    let dir = Path::new(contest);
-    if !dir.exists() {
-        bail!("{} does not exist", dir.display());
-    }
-    // oj test -c "cargo run --bin a -d "testcases/a"
-    println!("🧪  oj test");
-    
-    let run_cmd = if cfg!(target_os = "windows") {
-        format!("\"cargo run --bin {problem} --release\"")
+/// How long `kp submit` waits for the verdict.
+const JUDGE_WAIT_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Interval between submission status requests when the site does not ask for one.
+const STATUS_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Largest source AtCoder accepts (512 KiB).
+const SUBMISSION_SIZE_LIMIT: usize = 512 << 10;
+
+/// Time limit applied when judging a sample case (AtCoder's usual 2 sec).
+const DEFAULT_TIME_LIMIT: Duration = Duration::from_secs(2);
+
+/// Memory limit applied when judging a sample case (AtCoder's usual 1024 MiB).
+const DEFAULT_MEMORY_LIMIT: u64 = 1024 << 20;
+
+/// Number of stderr lines shown for a runtime error in `kp test`.
+const STDERR_TAIL_LINES: usize = 10;
+
+/// The generator and brute force get this many times the time limit in `kp stress`, as
+/// does the reference solution of `kp case add`.
+const NAIVE_TIME_LIMIT_FACTOR: u32 = 10;
+
+/// How often `kp watch` looks for changed files.
+const WATCH_INTERVAL: Duration = Duration::from_millis(300);
+
+/// Debug builds run much slower, so they get this many times the time limit in `kp debug`.
+const DEBUG_TIME_LIMIT_FACTOR: u32 = 10;
+
+/// Judge result of a single test case.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Verdict {
+    /// Accepted
+    Ac,
+    /// Wrong Answer
+    Wa,
+    /// Runtime Error (non-zero exit status)
+    Re,
+    /// Time Limit Exceeded
+    Tle,
+    /// Memory Limit Exceeded
+    Mle,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Verdict::Ac => "AC",
+            Verdict::Wa => "WA",
+            Verdict::Re => "RE",
+            Verdict::Tle => "TLE",
+            Verdict::Mle => "MLE",
+        })
+    }
+}
+
+/// kp's own configuration directory: `$KP_CONFIG_DIR`, else `%APPDATA%\kp` on Windows and
+/// `$XDG_CONFIG_HOME/kp` (or `~/.config/kp`) elsewhere.
+fn config_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("KP_CONFIG_DIR") {
+        return Some(PathBuf::from(dir));
+    }
+    let base = if cfg!(windows) {
+        PathBuf::from(std::env::var_os("APPDATA")?)
     } else {
-        format!("cargo run --bin {problem} --release")
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| Some(PathBuf::from(std::env::var_os("HOME")?).join(".config")))?
     };
+    Some(base.join("kp"))
+}
+
+/// Read `contest.acc.json` of the contest in `dir`.
+fn read_contest_json(dir: &Path) -> Result<Input> {
+    let json_path = dir.join("contest.acc.json");
+    let file =
+        fs::File::open(&json_path).with_context(|| format!("cannot open {:?}", json_path))?;
+    serde_json::from_reader(file).with_context(|| format!("{} is not valid", json_path.display()))
+}
+
+/// Task `problem` of the contest in `dir`, if `contest.acc.json` lists it.
+fn find_task(dir: &Path, problem: &str) -> Option<Task> {
+    read_contest_json(dir)
+        .ok()?
+        .tasks
+        .into_iter()
+        .find(|t| t.label.eq_ignore_ascii_case(problem))
+}
+
+/// Source file of `problem`: the task's `directory.submit` in `contest.acc.json`, falling
+/// back to the path of its bin in the manifest.
+fn submit_path(dir: &Path, problem: &str) -> Result<PathBuf> {
+    if let Some(task) = find_task(dir, problem) {
+        return Ok(dir.join(&task.directory.submit));
+    }
+    match manifest_bin_path(dir, problem)? {
+        Some(path) => Ok(dir.join(path)),
+        None => bail!(
+            "problem {} is neither in contest.acc.json nor in Cargo.toml",
+            problem
+        ),
+    }
+}
 
-    command("oj")
-        .current_dir(Path::new(&dir))
-        .args(["test", "-c", &run_cmd])
-        .args(["-d", &format!("testcases/{problem}")])
-        .status()?
-        .success()
-        .then_some(());
+/// Append a `[[bin]]` entry to a Cargo manifest unless a bin with `name` already exists.
+///
+/// Returns whether the manifest was changed.
+fn add_bin(doc: &mut DocumentMut, name: &str, path: &str) -> Result<bool> {
+    // ① Ensure [[bin]] is an ArrayOfTables, not a Value::Array
+    if doc.get("bin").is_none() {
+        doc["bin"] = Item::ArrayOfTables(ArrayOfTables::new());
+    }
+    let bins = doc["bin"]
+        .as_array_of_tables_mut() // ✅ correct accessor
+        .ok_or_else(|| anyhow::anyhow!("`bin` must be an array-of-tables"))?;
 
+    // ② Each element is &Table, so we can inspect keys normally
+    if bins
+        .iter()
+        .any(|tbl: &Table| tbl.get("name").and_then(|v| v.as_str()) == Some(name))
+    {
+        return Ok(false); // already present
+    }
+
+    // ③ Push a new table
+    let mut t = Table::new();
+    t["name"] = name.into();
+    t["path"] = path.into();
+    t.set_implicit(true); // no '{}' braces
+    bins.push(t);
+    Ok(true)
+}
+
+/// Source path of the bin called `name` in the contest manifest in `dir`, if there is one.
+fn manifest_bin_path(dir: &Path, name: &str) -> Result<Option<String>> {
+    let cargo_path = dir.join("Cargo.toml");
+    let doc = fs::read_to_string(&cargo_path)
+        .with_context(|| format!("cannot read {}", cargo_path.display()))?
+        .parse::<DocumentMut>()?;
+    let Some(bins) = doc.get("bin").and_then(Item::as_array_of_tables) else {
+        return Ok(None);
+    };
+    let path = bins
+        .iter()
+        .find(|tbl| tbl.get("name").and_then(|v| v.as_str()) == Some(name))
+        .map(|tbl| {
+            tbl.get("path")
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("src/bin/{name}.rs"))
+        });
+    Ok(path)
+}
+
+/// Make sure the contest manifest in `dir` has a `[[bin]]` named `name` at `path`.
+fn register_bin(dir: &Path, name: &str, path: &str) -> Result<()> {
+    let cargo_path = dir.join("Cargo.toml");
+    let mut doc = fs::read_to_string(&cargo_path)
+        .with_context(|| format!("cannot read {}", cargo_path.display()))?
+        .parse::<DocumentMut>()?;
+    if add_bin(&mut doc, name, path)? {
+        fs::write(&cargo_path, doc.to_string())?;
+        println!(
+            "Registered bin `{name}` ({path}) in {}",
+            cargo_path.display()
+        );
+    }
+    Ok(())
+}
+
+/// `kp test`
+fn test_problem(dir: &Path, problem: &str, jobs: usize, args: &JudgeArgs) -> Result<()> {
+    let exe = build_bin(dir, problem, true)?;
+    let summary = run_samples(dir, problem, &exe, jobs, args, false)?;
+
+    println!("{}", summary.describe());
+    let (passed, total) = (summary.passed, summary.total);
+    if passed < total {
+        bail!("{} of {} cases failed", total - passed, total);
+    }
     Ok(())
 }
 
+/// `kp test <contest>` / `kp test --all`
+fn test_contest(dir: &Path, jobs: usize, args: &JudgeArgs) -> Result<()> {
+    let rows = judge_contest(dir, jobs, args)?;
+    let (table, all_passed) = summary_table(&rows);
+    // Replace the progress line with the table.
+    print!("\r\x1b[2K");
+    for line in table {
+        println!("{line}");
+    }
+    if !all_passed {
+        bail!("some problems failed");
+    }
+    Ok(())
+}
+
+/// Outcome of one problem in `kp test --all`.
+enum ContestRow {
+    CompileError,
+    NoCases,
+    Judged(Result<TestSummary>),
+}
+
+/// Build every problem of the contest at `dir` and judge each one against its test cases.
+fn judge_contest(dir: &Path, jobs: usize, args: &JudgeArgs) -> Result<Vec<(String, ContestRow)>> {
+    let input = read_contest_json(dir)?;
+    let problems: Vec<String> = input.tasks.iter().map(|t| t.label.to_lowercase()).collect();
+    // A half-written task must not keep the others from being tested.
+    let executables = build_bins(dir, &problems, true)?;
+
+    let mut rows = Vec::new();
+    for problem in problems {
+        let Some(exe) = executables.get(&problem) else {
+            rows.push((problem, ContestRow::CompileError));
+            continue;
+        };
+        print!("\r🧪  testing {problem}…");
+        std::io::stdout().flush()?;
+        let row = if dir.join("testcases").join(&problem).exists() {
+            ContestRow::Judged(run_samples(dir, &problem, exe, jobs, args, true))
+        } else {
+            ContestRow::NoCases
+        };
+        rows.push((problem, row));
+    }
+    Ok(rows)
+}
+
+/// Lay out `rows` as a table with a header line, and tell whether every problem passed.
+fn summary_table(rows: &[(String, ContestRow)]) -> (Vec<String>, bool) {
+    let mut table = vec![format!("{:<8}{:>8}  Failures", "Problem", "Passed")];
+    let mut all_passed = true;
+    for (problem, row) in rows {
+        let problem = problem.to_uppercase();
+        let line = match row {
+            ContestRow::CompileError => {
+                all_passed = false;
+                format!("{:<8}{:>8}  ❌ CE", problem, "-")
+            }
+            ContestRow::NoCases => format!("{:<8}{:>8}  (no test cases)", problem, "-"),
+            ContestRow::Judged(Err(err)) => {
+                all_passed = false;
+                format!("{:<8}{:>8}  ❌ {}", problem, "-", err)
+            }
+            ContestRow::Judged(Ok(summary)) => {
+                let mark = if summary.failures.is_empty() {
+                    "✅"
+                } else {
+                    "❌"
+                };
+                all_passed &= summary.failures.is_empty();
+                format!(
+                    "{:<8}{:>8}  {} {}",
+                    problem,
+                    format!("{}/{}", summary.passed, summary.total),
+                    mark,
+                    summary.failure_list()
+                )
+            }
+        };
+        table.push(line);
+    }
+    (table, all_passed)
+}
+
+/// Outcome of judging every test case of one problem.
+struct TestSummary {
+    passed: usize,
+    total: usize,
+    /// Name and verdict of each case that did not pass.
+    failures: Vec<(String, Verdict)>,
+}
+
+impl TestSummary {
+    /// One line such as `✅ 3/3 passed` or `❌ 1/3 passed: WA sample-2, TLE custom-1`.
+    fn describe(&self) -> String {
+        if self.failures.is_empty() {
+            return format!("✅ {}/{} passed", self.passed, self.total);
+        }
+        format!(
+            "❌ {}/{} passed: {}",
+            self.passed,
+            self.total,
+            self.failure_list()
+        )
+    }
+
+    /// The failing cases with their verdicts, e.g. `WA sample-2, TLE custom-1`.
+    fn failure_list(&self) -> String {
+        let failures: Vec<String> = self
+            .failures
+            .iter()
+            .map(|(case, verdict)| format!("{verdict} {case}"))
+            .collect();
+        failures.join(", ")
+    }
+}
+
+/// Judge `exe` against every test case of `problem`, printing each verdict unless `quiet`.
+fn run_samples(
+    dir: &Path,
+    problem: &str,
+    exe: &Path,
+    jobs: usize,
+    args: &JudgeArgs,
+    quiet: bool,
+) -> Result<TestSummary> {
+    let testcase_dir = dir.join("testcases").join(problem);
+    let samples = collect_samples(&testcase_dir)?;
+    let judge = Judge {
+        quiet,
+        ..Judge::new(dir, problem, args)?
+    };
+    if !judge.quiet {
+        let task = find_task(dir, problem);
+        let name = task.as_ref().map_or(problem.to_string(), Task::describe);
+        println!("🧪  test {name} ({} cases)", samples.len());
+        if let Some(url) = task.and_then(|t| t.url) {
+            println!("🔗  {url}");
+        }
+    }
+
+    let mut summary = TestSummary {
+        passed: 0,
+        total: samples.len(),
+        failures: Vec::new(),
+    };
+    run_ordered(
+        &samples,
+        jobs,
+        |sample_in| -> Result<_> {
+            let stem = sample_in.file_stem().unwrap().to_string_lossy().to_string();
+            let sample_out = testcase_dir.join(format!("{}.out", stem));
+            let input = read_case_file(sample_in)?;
+            let expected = read_case_file(&sample_out)?;
+
+            let execution = run_binary(dir, exe, &input, Some(judge.time_limit))?;
+            let (verdict, note) = judge.verdict(&stem, &input, &execution, &expected)?;
+            Ok((stem, verdict, execution, note))
+        },
+        |result| {
+            let (stem, verdict, execution, note) = result?;
+            judge.report(&stem, verdict, &execution, note.as_deref());
+            if verdict == Verdict::Ac {
+                summary.passed += 1;
+            } else {
+                summary.failures.push((stem, verdict));
+            }
+            Ok(())
+        },
+    )?;
+    Ok(summary)
+}
+
 /// `kp debug`
-fn debug_problem(contest: &str, problem: &str) -> Result<()> {
-    let dir = Path::new(contest);
-    if !dir.exists() {
-        bail!("{} does not exist", dir.display());
+fn debug_problem(dir: &Path, problem: &str, jobs: usize, args: &JudgeArgs) -> Result<()> {
+    let testcase_dir = dir.join("testcases").join(problem);
+    let samples = collect_samples(&testcase_dir)?;
+    let judge = Judge::new(dir, problem, args)?;
+    let time_limit = judge.time_limit;
+    // 各プロファイルにつき一度だけビルドする
+    let debug_exe = build_bin(dir, problem, false)?;
+    let release_exe = build_bin(dir, problem, true)?;
+
+    // Run every case first (possibly in parallel), then print them in order.
+    let run_case = |sample_in: &PathBuf| -> Result<DebugCase> {
+        let stem = sample_in.file_stem().unwrap().to_string_lossy().to_string();
+        // sample-1.in → sample-1.out
+        let sample_out = testcase_dir.join(format!("{}.out", stem));
+        let input = read_case_file(sample_in)?;
+        let debug_output = run_binary(
+            dir,
+            &debug_exe,
+            &input,
+            Some(time_limit * DEBUG_TIME_LIMIT_FACTOR),
+        )?;
+        let release_output = run_binary(dir, &release_exe, &input, Some(time_limit))?;
+        let expected = read_case_file(&sample_out)?;
+        let judged = if judge.timed_out(&release_output)
+            || release_output
+                .peak_memory
+                .is_some_and(|m| m > judge.memory_limit)
+            || !release_output.status.success()
+        {
+            None
+        } else {
+            Some(judge.judge_output(&stem, &input, &release_output.stdout, &expected)?)
+        };
+        Ok(DebugCase {
+            stem,
+            input,
+            debug_output,
+            release_output,
+            expected,
+            judged,
+        })
+    };
+
+    run_ordered(&samples, jobs, run_case, |case| {
+        let case = case?;
+        println!("==================== [{}] ====================", case.stem);
+        // 入力ファイル
+        println!("[input]");
+        println!("{}", case.input);
+
+        // debugビルド
+        println!("[debug output]");
+        let debug_output = &case.debug_output;
+        println!("{}", debug_output.stdout);
+        print_stderr("[debug stderr]", debug_output);
+        if debug_output.timed_out {
+            println!("[⏱ TLE] Killed after {:?}", debug_output.elapsed);
+        } else if let Some(reason) = debug_output.failure_reason() {
+            println!("[💥 RE] {reason}");
+        }
+
+        // releaseビルド
+        println!("[output]");
+        let release_output = &case.release_output;
+        println!("{}", release_output.stdout);
+        print_stderr("[stderr]", release_output);
+        match release_output.peak_memory {
+            Some(memory) => println!(
+                "Execution Time: {:?}, Memory: {}",
+                release_output.elapsed,
+                format_memory(memory)
+            ),
+            None => println!("Execution Time: {:?}", release_output.elapsed),
+        }
+        if judge.timed_out(release_output) {
+            println!("[⏱ TLE] Exceeded the time limit of {:?}", time_limit);
+        }
+
+        // 期待値
+        println!("[expect]");
+        println!("{}", case.expected);
+
+        // 比較
+        println!("[comparison result]");
+        if judge.timed_out(release_output) {
+            println!("[❌ Failed] Time limit exceeded.");
+        } else if release_output
+            .peak_memory
+            .is_some_and(|m| m > judge.memory_limit)
+        {
+            println!("[❌ Failed] Memory limit exceeded.");
+        } else if let Some(reason) = release_output.failure_reason() {
+            println!("[❌ Failed] Runtime error: {reason}");
+        } else if let Some((accepted, note)) = &case.judged {
+            match (accepted, judge.checker.is_some()) {
+                (true, false) => println!("[✅ Complete] Output matches expected output."),
+                (true, true) => println!("[✅ Complete] Checker accepted the output."),
+                (false, false) => println!("[❌ Failed] Output does not match expected output."),
+                (false, true) => println!("[❌ Failed] Checker rejected the output."),
+            }
+            if let Some(note) = note {
+                println!("{}", note.trim_end());
+            }
+        }
+        println!();
+        Ok(())
+    })
+}
+
+/// Everything `kp debug` shows for one test case.
+struct DebugCase {
+    stem: String,
+    input: String,
+    debug_output: Execution,
+    release_output: Execution,
+    expected: String,
+    /// Judgement of the release output, when it exited normally.
+    judged: Option<(bool, Option<String>)>,
+}
+
+/// `kp watch`
+fn watch_problem(dir: &Path, problem: &str, jobs: usize, args: &JudgeArgs) -> Result<()> {
+    let source = submit_path(dir, problem)?;
+    let testcase_dir = dir.join("testcases").join(problem);
+    let watched = [source.clone(), testcase_dir.clone()];
+
+    let mut last = None;
+    loop {
+        let snapshot = modification_times(&watched);
+        if last.as_ref() == Some(&snapshot) {
+            std::thread::sleep(WATCH_INTERVAL);
+            continue;
+        }
+        if last.is_some() {
+            // Let the editor finish writing before we build.
+            std::thread::sleep(WATCH_INTERVAL);
+        }
+        last = Some(modification_times(&watched));
+
+        // Clear the screen and start over at the top-left corner.
+        print!("\x1b[2J\x1b[H");
+        println!(
+            "👀  watching {} and {} (Ctrl-C to quit)",
+            source.display(),
+            testcase_dir.display()
+        );
+        let summary = build_bin(dir, problem, true)
+            .and_then(|exe| run_samples(dir, problem, &exe, jobs, args, true));
+        match summary {
+            Ok(summary) => println!("{}", summary.describe()),
+            Err(err) => println!("Error: {err}"),
+        }
+    }
+}
+
+/// Modification times of `paths` and, for directories, of the files directly inside them.
+fn modification_times(paths: &[PathBuf]) -> Vec<(PathBuf, Option<std::time::SystemTime>)> {
+    let mut times = Vec::new();
+    for path in paths {
+        let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+        times.push((path.clone(), modified));
+        if let Ok(entries) = fs::read_dir(path) {
+            for entry in entries.flatten() {
+                let modified = entry.metadata().and_then(|m| m.modified()).ok();
+                times.push((entry.path(), modified));
+            }
+        }
+    }
+    times.sort();
+    times
+}
+
+/// `kp interactive`
+fn interactive_problem(
+    dir: &Path,
+    problem: &str,
+    judge: Option<&str>,
+    time_limit: Duration,
+) -> Result<()> {
+    let Some(judge_exe) = resolve_problem_bin(dir, problem, "judge", judge)? else {
+        bail!("No judge found: write testcases/{problem}/judge.rs or pass --judge <BIN|PATH>");
+    };
+    let exe = build_bin(dir, problem, true)?;
+
+    // Every `*.in` file is handed to the judge; without any, run a single session.
+    let testcase_dir = dir.join("testcases").join(problem);
+    let cases: Vec<Option<PathBuf>> = match collect_samples(&testcase_dir) {
+        Ok(samples) => samples
+            .into_iter()
+            .map(|p| fs::canonicalize(p).map(Some))
+            .collect::<std::io::Result<_>>()?,
+        Err(_) => vec![None],
+    };
+
+    let mut passed = 0;
+    for case in &cases {
+        let name = case
+            .as_ref()
+            .map(|p| p.file_stem().unwrap().to_string_lossy().to_string())
+            .unwrap_or_else(|| "session".to_string());
+        println!("==================== [{}] ====================", name);
+        let session = interactive::run_session(dir, &exe, &judge_exe, case.as_deref(), time_limit)?;
+        println!("[{:<3}] {}  {}", session.verdict, name, session.stats());
+        if !session.judge_message.is_empty() {
+            println!("[judge]");
+            println!("{}", session.judge_message);
+        }
+        if session.verdict == Verdict::Re {
+            if let Some(reason) = session.failure_reason() {
+                println!("[💥 RE] {reason}");
+            }
+        }
+        if !session.solution_stderr.is_empty() {
+            println!("[stderr]");
+            print!("{}", session.solution_stderr);
+        }
+        if session.verdict == Verdict::Ac {
+            passed += 1;
+        }
+        println!();
+    }
+
+    let total = cases.len();
+    if passed == total {
+        println!("✅ {passed}/{total} passed");
+        Ok(())
+    } else {
+        println!("❌ {passed}/{total} passed");
+        bail!("{} of {} sessions failed", total - passed, total);
+    }
+}
+
+/// Settings of `kp stress` besides the judge options.
+struct StressOptions {
+    gen: String,
+    naive: String,
+    iterations: u64,
+    seed: Option<u64>,
+    format: Option<PathBuf>,
+    minimize: bool,
+}
+
+/// `kp stress`
+fn stress_problem(
+    dir: &Path,
+    problem: &str,
+    options: &StressOptions,
+    args: &JudgeArgs,
+) -> Result<()> {
+    let (gen, naive, iterations) = (&options.gen, &options.naive, options.iterations);
+    ensure_sibling_bin(dir, problem, gen)?;
+    ensure_sibling_bin(dir, problem, naive)?;
+    let judge = Judge::new(dir, problem, args)?;
+    let exe = build_bin(dir, problem, true)?;
+    let gen_exe = build_bin(dir, gen, true)?;
+    let naive_exe = build_bin(dir, naive, true)?;
+    let helper_limit = Some(judge.time_limit * NAIVE_TIME_LIMIT_FACTOR);
+
+    let testcase_dir = dir.join("testcases").join(problem);
+    let format: Box<dyn InputFormat> = match &options.format {
+        Some(path) => Box::new(Described::parse(&fs::read_to_string(path)?)?),
+        None if testcase_dir.join("format.txt").exists() => Box::new(Described::parse(
+            &fs::read_to_string(testcase_dir.join("format.txt"))?,
+        )?),
+        None => Box::new(FreeForm),
+    };
+
+    let first_seed = options.seed.unwrap_or_else(|| {
+        std::time::UNIX_EPOCH
+            .elapsed()
+            .map(|d| d.as_secs())
+            .unwrap_or_default()
+    });
+    println!("🔁  stress {problem} against {naive} ({iterations} inputs from seed {first_seed})");
+
+    for i in 0..iterations {
+        let seed = first_seed.wrapping_add(i);
+        print!("\r{}/{} (seed {})", i + 1, iterations, seed);
+        std::io::stdout().flush()?;
+
+        let generated = run_binary_with_args(dir, &gen_exe, &[seed.to_string()], "", helper_limit)?;
+        if let Some(reason) = generated.failure_reason() {
+            println!();
+            bail!("generator failed on seed {}: {}", seed, reason);
+        }
+        let input = generated.stdout;
+        let reference = run_binary(dir, &naive_exe, &input, helper_limit)?;
+        if let Some(reason) = reference.failure_reason() {
+            println!();
+            print!("[input]\n{input}");
+            bail!("{} failed on seed {}: {}", naive, seed, reason);
+        }
+
+        let case = format!("seed-{seed}");
+        let execution = run_binary(dir, &exe, &input, Some(judge.time_limit))?;
+        let (verdict, _) = judge.verdict(&case, &input, &execution, &reference.stdout)?;
+        if verdict == Verdict::Ac {
+            continue;
+        }
+        println!();
+        println!("❗ seed {seed}: {verdict}");
+
+        // Shrink the input as long as the solution keeps failing the same way.
+        let input = if options.minimize {
+            let original_len = input.len();
+            let minimized = shrink::minimize(
+                &input,
+                format.as_ref(),
+                |candidate| {
+                    let reference = run_binary(dir, &naive_exe, candidate, helper_limit)?;
+                    if reference.timed_out || !reference.status.success() {
+                        return Ok(false);
+                    }
+                    let execution = run_binary(dir, &exe, candidate, Some(judge.time_limit))?;
+                    let (v, _) = judge.verdict(&case, candidate, &execution, &reference.stdout)?;
+                    Ok(v == verdict)
+                },
+                |current| {
+                    // Clear the line first: the count only ever goes down.
+                    print!("\x1b[2K\rshrinking: {} bytes", current.len());
+                    let _ = std::io::stdout().flush();
+                },
+            )?;
+            println!(
+                "\x1b[2K\rshrunk from {} to {} bytes",
+                original_len,
+                minimized.len()
+            );
+            minimized
+        } else {
+            input
+        };
+
+        // Re-run on the final input so that what we show matches what we save.
+        let reference = run_binary(dir, &naive_exe, &input, helper_limit)?;
+        let execution = run_binary(dir, &exe, &input, Some(judge.time_limit))?;
+        let (verdict, note) = judge.verdict(&case, &input, &execution, &reference.stdout)?;
+        println!("[input]");
+        println!("{}", input);
+        println!("[expect] ({naive})");
+        println!("{}", reference.stdout);
+        println!("[output]");
+        println!("{}", execution.stdout);
+        print_verdict(&case, verdict, &execution, note.as_deref());
+
+        let saved = save_case(&testcase_dir, "stress", &input, &reference.stdout)?;
+        println!("Saved as {}", saved.display());
+        bail!("found a failing input (seed {seed})");
+    }
+    println!();
+    println!("✅ no difference in {iterations} inputs");
+    Ok(())
+}
+
+/// `kp import`
+fn import_samples(dir: &Path, problem: &str, page: &Path) -> Result<()> {
+    let html = fs::read(page).with_context(|| format!("Failed to read {}", page.display()))?;
+    let samples = atcoder::parse_samples(&String::from_utf8_lossy(&html));
+    if samples.is_empty() {
+        bail!("no samples found in {}", page.display());
+    }
+    let testcase_dir = dir.join("testcases").join(problem);
+    write_samples(&testcase_dir, &samples)?;
+    println!(
+        "📥  imported {} samples into {}",
+        samples.len(),
+        testcase_dir.display()
+    );
+    Ok(())
+}
+
+/// `kp run`
+fn run_problem(dir: &Path, problem: &str, input: Option<&Path>) -> Result<()> {
+    let exe = build_bin(dir, problem, true)?;
+    let stdin = match input {
+        Some(path) => Stdio::from(
+            File::open(path).with_context(|| format!("Failed to open {}", path.display()))?,
+        ),
+        None => {
+            if std::io::stdin().is_terminal() {
+                let eof = if cfg!(windows) {
+                    "Ctrl-Z, Enter"
+                } else {
+                    "Ctrl-D"
+                };
+                eprintln!("⌨️  reading input from the terminal (end with {eof})");
+            }
+            Stdio::inherit()
+        }
+    };
+    let execution = run_binary_live(dir, &exe, stdin)?;
+
+    // Statistics go to stderr so that stdout holds nothing but the solution's output.
+    let memory = execution
+        .peak_memory
+        .map(|m| format!("  {}", format_memory(m)))
+        .unwrap_or_default();
+    eprintln!("⏱  {} ms{}", execution.elapsed.as_millis(), memory);
+    if let Some(reason) = execution.failure_reason() {
+        bail!("{} failed: {}", problem, reason);
+    }
+    Ok(())
+}
+
+/// `kp bundle`: returns the path of the bundled file.
+fn bundle_problem(dir: &Path, problem: &str) -> Result<PathBuf> {
+    let solution_path = submit_path(dir, problem)?;
+    let solution = fs::read_to_string(&solution_path)
+        .with_context(|| format!("Failed to read {}", solution_path.display()))?;
+    let bundle = bundle::bundle(dir, &solution)?;
+    for (name, kept, total) in &bundle.libraries {
+        match total {
+            0 => println!("📚  {name}"),
+            _ => println!("📚  {name}: {kept} of {total} items"),
+        }
+    }
+    bundle::verify(dir, problem, &bundle.source)?;
+
+    let path = dir.join("submit").join(format!("{problem}.rs"));
+    fs::create_dir_all(dir.join("submit"))?;
+    fs::write(&path, &bundle.source)?;
+    println!("📦  {} ({} bytes)", path.display(), bundle.source.len());
+    if bundle.source.len() > SUBMISSION_SIZE_LIMIT {
+        println!("⚠️  larger than the {SUBMISSION_SIZE_LIMIT} bytes AtCoder accepts");
+    }
+    Ok(path)
+}
+
+struct SubmitOptions {
+    force: bool,
+    language_id: Option<String>,
+    /// Wait for the verdict.
+    wait: bool,
+    base_url: String,
+}
+
+/// `kp submit`: returns the ID of the submission.
+fn submit_problem(
+    dir: &Path,
+    problem: &str,
+    options: &SubmitOptions,
+    args: &JudgeArgs,
+) -> Result<u64> {
+    if let Err(err) = test_problem(dir, problem, 1, args) {
+        if !options.force {
+            bail!("{}; not submitting (pass --force to submit anyway)", err);
+        }
+        println!("⚠️  {err}; submitting anyway");
+    }
+    let path = bundle_problem(dir, problem)?;
+    let source = fs::read_to_string(&path)?;
+
+    let input = read_contest_json(dir)?;
+    let contest = match input.contest {
+        Some(contest) => contest.id,
+        None => fs::canonicalize(dir)?
+            .file_name()
+            .context("cannot tell the contest ID from the directory")?
+            .to_string_lossy()
+            .to_string(),
+    };
+    let task_id = find_task(dir, problem)
+        .and_then(|task| task.id)
+        .unwrap_or_else(|| format!("{contest}_{problem}"));
+
+    let client = atcoder_client(&options.base_url)?;
+    let form = client.submit_form(&contest)?;
+    let language_id = match &options.language_id {
+        Some(id) => id.clone(),
+        None => {
+            let rust = form
+                .languages
+                .iter()
+                .find(|(_, name)| name.starts_with("Rust (rustc"))
+                .or_else(|| {
+                    form.languages
+                        .iter()
+                        .find(|(_, name)| name.starts_with("Rust"))
+                })
+                .context("the submit form offers no Rust; pass --language-id")?;
+            println!("🦀  {} (language {})", rust.1, rust.0);
+            rust.0.clone()
+        }
+    };
+    let id = client.submit(&contest, &task_id, &language_id, &source, &form.csrf_token)?;
+    let url = client.url(&format!("/contests/{contest}/submissions/{id}"));
+    println!("🚀  submitted {task_id}: {url}");
+
+    let status = if options.wait {
+        wait_for_judge(&client, &contest, id)?
+    } else {
+        None
+    };
+    let submitted_at = std::time::UNIX_EPOCH
+        .elapsed()
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    record_submission(
+        dir,
+        &json!({
+            "id": id,
+            "problem": problem,
+            "task": task_id,
+            "language": language_id,
+            "submitted_at": submitted_at,
+            "status": status.as_ref().map(|s| &s.status),
+            "time": status.as_ref().and_then(|s| s.time.as_ref()),
+            "memory": status.as_ref().and_then(|s| s.memory.as_ref()),
+            "score": status.as_ref().and_then(|s| s.score.as_ref()),
+            "url": url,
+        }),
+    )?;
+
+    match status {
+        Some(status) if status.status != "AC" => bail!("submission {} got {}", id, status.status),
+        _ => Ok(id),
+    }
+}
+
+/// Poll the state of submission `id` with a live progress line until it is judged.
+///
+/// Returns `None` when judging takes longer than [`JUDGE_WAIT_TIMEOUT`].
+fn wait_for_judge(
+    client: &atcoder::Client,
+    contest: &str,
+    id: u64,
+) -> Result<Option<atcoder::SubmissionStatus>> {
+    let start = std::time::Instant::now();
+    loop {
+        let status = client.submission_status(contest, id)?;
+        if status.is_final() {
+            let icon = if status.status == "AC" { "✅" } else { "❌" };
+            let details: Vec<&str> = [&status.time, &status.memory]
+                .into_iter()
+                .flatten()
+                .map(String::as_str)
+                .collect();
+            println!("\r\x1b[2K{icon} {}  {}", status.status, details.join("  "));
+            return Ok(Some(status));
+        }
+        print!(
+            "\r\x1b[2K⏳ {} ({} s)",
+            status.status,
+            start.elapsed().as_secs()
+        );
+        std::io::stdout().flush()?;
+        if start.elapsed() > JUDGE_WAIT_TIMEOUT {
+            println!();
+            println!("⌛  still judging; giving up waiting");
+            return Ok(None);
+        }
+        std::thread::sleep(status.interval.unwrap_or(STATUS_POLL_INTERVAL));
+    }
+}
+
+/// Append `entry` to the submission log of the contest, `.kp/submissions.jsonl`.
+fn record_submission(dir: &Path, entry: &Value) -> Result<()> {
+    let path = dir.join(".kp").join("submissions.jsonl");
+    fs::create_dir_all(dir.join(".kp"))?;
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    writeln!(file, "{entry}")?;
+    Ok(())
+}
+
+/// Client for `base_url`, logged in with the session in `KP_SESSION`, else the one saved by
+/// `kp login`, if there is one.
+fn atcoder_client(base_url: &str) -> Result<atcoder::Client> {
+    let session = match std::env::var("KP_SESSION") {
+        Ok(session) => Some(session),
+        Err(_) => session::load(base_url)?.map(|saved| saved.session),
+    };
+    Ok(atcoder::Client::new(base_url).with_session(session))
+}
+
+/// `kp check`
+fn check_contest(dir: &Path, problem: Option<&str>) -> Result<()> {
+    let judge = check::JudgeCrates::load()?;
+    let warnings = check::check(dir, problem, &judge)?;
+    for warning in &warnings {
+        println!("⚠️  {warning}");
+    }
+    if warnings.is_empty() {
+        println!(
+            "✅ everything is available on the judge ({})",
+            judge.language
+        );
+    } else {
+        println!(
+            "⚠️  {} problem(s) with the judge's crates ({})",
+            warnings.len(),
+            judge.language
+        );
+    }
+    Ok(())
+}
+
+/// `kp login`
+fn login(base_url: &str, username: Option<String>) -> Result<()> {
+    let username = match username {
+        Some(username) => username,
+        None => {
+            eprint!("Username: ");
+            std::io::stderr().flush()?;
+            read_line()?
+        }
+    };
+    let password = if std::io::stdin().is_terminal() {
+        rpassword::prompt_password("Password: ")?
+    } else {
+        read_line()?
+    };
+    if username.is_empty() || password.is_empty() {
+        bail!("a username and a password are required");
+    }
+
+    let client = atcoder::Client::new(base_url);
+    client.login(&username, &password)?;
+    let user = client
+        .whoami()?
+        .context("the site did not keep the login; try again")?;
+    let session = client
+        .session()
+        .context("the site sent no session cookie")?;
+    let saved_at = std::time::UNIX_EPOCH
+        .elapsed()
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    session::save(
+        base_url,
+        session::Session {
+            session,
+            user: user.clone(),
+            saved_at,
+        },
+    )?;
+    println!("🔑  logged in as {user}");
+    Ok(())
+}
+
+/// `kp logout`
+fn logout(base_url: &str) -> Result<()> {
+    if session::remove(base_url)? {
+        println!("👋  logged out of {base_url}");
+    } else {
+        println!("not logged in to {base_url}");
+    }
+    Ok(())
+}
+
+/// `kp whoami`
+fn whoami(base_url: &str) -> Result<()> {
+    let saved = session::load(base_url)?;
+    if std::env::var_os("KP_SESSION").is_none() && saved.is_none() {
+        bail!("not logged in to {}; run `kp login`", base_url);
+    }
+    match atcoder_client(base_url)?.whoami()? {
+        Some(user) => {
+            println!("{user}");
+            Ok(())
+        }
+        None => bail!("the session has expired; run `kp login` again"),
+    }
+}
+
+/// One line of stdin without the line ending.
+fn read_line() -> Result<String> {
+    let mut line = String::new();
+    std::io::stdin().read_line(&mut line)?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// `kp case add`
+fn add_case(
+    dir: &Path,
+    problem: &str,
+    reference: Option<&str>,
+    expect: Option<&Path>,
+    edit: bool,
+) -> Result<()> {
+    let input = if edit || std::io::stdin().is_terminal() {
+        edit_text(dir, &format!("{problem}.in"))?
+    } else {
+        let mut input = String::new();
+        std::io::stdin().read_to_string(&mut input)?;
+        input
+    };
+    if input.trim().is_empty() {
+        bail!("the input is empty; nothing was added");
+    }
+
+    let output = match (reference, expect) {
+        (Some(reference), _) => {
+            ensure_sibling_bin(dir, problem, reference)?;
+            let exe = build_bin(dir, reference, true)?;
+            // A brute force gets the same slack as in `kp stress`.
+            let time_limit = find_task(dir, problem)
+                .and_then(|task| task.time_limit())
+                .unwrap_or(DEFAULT_TIME_LIMIT);
+            let time_limit = time_limit * NAIVE_TIME_LIMIT_FACTOR;
+            let execution = run_binary(dir, &exe, &input, Some(time_limit))?;
+            if let Some(reason) = execution.failure_reason() {
+                print_stderr("[stderr]", &execution);
+                bail!("{} failed: {}", reference, reason);
+            }
+            execution.stdout
+        }
+        (None, Some(path)) => read_case_file(path)?,
+        (None, None) if std::io::stdin().is_terminal() => {
+            edit_text(dir, &format!("{problem}.out"))?
+        }
+        (None, None) => bail!("pass --reference or --expect to give the expected output"),
+    };
+
+    let testcase_dir = dir.join("testcases").join(problem);
+    let path = save_case(&testcase_dir, "custom", &input, &output)?;
+    println!("💾  added {}", path.display());
+    Ok(())
+}
+
+/// `kp case list`
+fn list_cases(dir: &Path, problem: &str) -> Result<()> {
+    let testcase_dir = dir.join("testcases").join(problem);
+    for path in collect_samples(&testcase_dir)? {
+        let stem = path.file_stem().unwrap().to_string_lossy().to_string();
+        let input = read_case_file(&path)?;
+        let preview = input.lines().next().unwrap_or_default();
+        let preview = match preview.char_indices().nth(40) {
+            Some((end, _)) => format!("{}…", &preview[..end]),
+            None => preview.to_string(),
+        };
+        println!(
+            "{:<12} {:<7} {:>4} lines  {}",
+            stem,
+            case_kind(&stem),
+            input.lines().count(),
+            preview
+        );
+    }
+    Ok(())
+}
+
+/// `kp case show`
+fn show_case(dir: &Path, problem: &str, name: &str) -> Result<()> {
+    let (input, output) = case_paths(dir, problem, name)?;
+    println!("[input]");
+    print!("{}", read_case_file(&input)?);
+    println!("[expect]");
+    if output.exists() {
+        print!("{}", read_case_file(&output)?);
+    }
+    Ok(())
+}
+
+/// `kp case rm`
+fn remove_case(dir: &Path, problem: &str, name: &str, force: bool) -> Result<()> {
+    let (input, output) = case_paths(dir, problem, name)?;
+    let stem = input.file_stem().unwrap().to_string_lossy().to_string();
+    if case_kind(&stem) == "sample" && !force {
+        bail!("{stem} is a downloaded sample; pass --force to delete it anyway");
+    }
+    fs::remove_file(&input)?;
+    if output.exists() {
+        fs::remove_file(&output)?;
+    }
+    println!("🗑️  removed {stem}");
+    Ok(())
+}
+
+/// Where a case comes from, judging by its name.
+fn case_kind(stem: &str) -> &'static str {
+    match stem.rsplit_once('-').map(|(prefix, _)| prefix) {
+        Some("sample") => "sample",
+        Some("custom") => "custom",
+        Some("stress") => "stress",
+        _ => "other",
     }
+}
+
+/// Input and expected output files of the case `name`; a bare number means `custom-N`.
+fn case_paths(dir: &Path, problem: &str, name: &str) -> Result<(PathBuf, PathBuf)> {
+    let name = name.trim_end_matches(".in").trim_end_matches(".out");
+    let stem = if name.chars().all(|c| c.is_ascii_digit()) {
+        format!("custom-{name}")
+    } else {
+        name.to_string()
+    };
     let testcase_dir = dir.join("testcases").join(problem);
+    let input = testcase_dir.join(format!("{stem}.in"));
+    if !input.exists() {
+        bail!("no case named {} in {}", stem, testcase_dir.display());
+    }
+    Ok((input, testcase_dir.join(format!("{stem}.out"))))
+}
+
+/// Let the user write a text in `$VISUAL`/`$EDITOR`, using a scratch file called `name`.
+fn edit_text(dir: &Path, name: &str) -> Result<String> {
+    let scratch_dir = dir.join("target").join("kp").join("case");
+    fs::create_dir_all(&scratch_dir)?;
+    let path = scratch_dir.join(name);
+    fs::write(&path, "")?;
+
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| if cfg!(windows) { "notepad" } else { "vi" }.to_string());
+    // The editor may come with arguments, e.g. `code --wait`.
+    let mut words = editor.split_whitespace();
+    let program = words.next().context("$EDITOR is empty")?;
+    let status = Command::new(program)
+        .args(words)
+        .arg(&path)
+        .status()
+        .with_context(|| format!("Failed to start editor `{}`", editor))?;
+    if !status.success() {
+        bail!("editor `{}` exited with {}", editor, status);
+    }
+    read_case_file(&path)
+}
+
+/// Everything needed to turn a run of a solution into a verdict.
+struct Judge {
+    time_limit: Duration,
+    memory_limit: u64,
+    comparator: Comparator,
+    checker: Option<Checker>,
+    /// Leave the per-case verdicts out, for callers that print a summary of their own.
+    quiet: bool,
+}
+
+impl Judge {
+    fn new(dir: &Path, problem: &str, args: &JudgeArgs) -> Result<Judge> {
+        let task = find_task(dir, problem);
+        Ok(Judge {
+            time_limit: args.time_limit(task.as_ref()),
+            memory_limit: args.memory_limit(task.as_ref()),
+            comparator: args.comparator(),
+            checker: Checker::resolve(dir, problem, args.checker.as_deref())?,
+            quiet: false,
+        })
+    }
+
+    /// Print the verdict of one test case, unless the judge is quiet.
+    fn report(&self, case: &str, verdict: Verdict, execution: &Execution, note: Option<&str>) {
+        if !self.quiet {
+            print_verdict(case, verdict, execution, note);
+        }
+    }
+
+    /// Judge one run of the solution on test case `case`.
+    ///
+    /// A wrong answer comes with a note (checker message or diff) explaining it.
+    fn verdict(
+        &self,
+        case: &str,
+        input: &str,
+        execution: &Execution,
+        expected: &str,
+    ) -> Result<(Verdict, Option<String>)> {
+        if self.timed_out(execution) {
+            return Ok((Verdict::Tle, None));
+        }
+        if execution.peak_memory.is_some_and(|m| m > self.memory_limit) {
+            return Ok((Verdict::Mle, None));
+        }
+        if !execution.status.success() {
+            return Ok((Verdict::Re, None));
+        }
+        let (accepted, note) = self.judge_output(case, input, &execution.stdout, expected)?;
+        let verdict = if accepted { Verdict::Ac } else { Verdict::Wa };
+        Ok((verdict, note))
+    }
+
+    /// Whether a run exceeded the time limit, either killed for it or finishing just past it
+    /// between two polls.
+    fn timed_out(&self, execution: &Execution) -> bool {
+        execution.timed_out || execution.elapsed > self.time_limit
+    }
+
+    /// Judge the output of a run that exited normally.
+    ///
+    /// Uses the checker when there is one and the comparator otherwise. Returns whether the
+    /// output is accepted, plus the checker's message or a diff explaining a rejection.
+    fn judge_output(
+        &self,
+        case: &str,
+        input: &str,
+        actual: &str,
+        expected: &str,
+    ) -> Result<(bool, Option<String>)> {
+        match &self.checker {
+            Some(checker) => {
+                let outcome = checker.check(case, input, actual, expected)?;
+                let message = Some(outcome.message).filter(|m| !m.is_empty());
+                Ok((outcome.accepted, message))
+            }
+            None => {
+                let diff = self.comparator.diff(actual, expected, use_color());
+                Ok((diff.is_none(), diff))
+            }
+        }
+    }
+}
+
+/// Print the one-line result of a test case, followed by the diff, checker message or
+/// runtime error details that explain a failure.
+fn print_verdict(name: &str, verdict: Verdict, execution: &Execution, note: Option<&str>) {
+    let memory = execution.peak_memory.map(format_memory).unwrap_or_default();
+    println!(
+        "[{:<3}] {}  {} ms  {}",
+        verdict,
+        name,
+        execution.elapsed.as_millis(),
+        memory
+    );
+    if verdict == Verdict::Wa {
+        for line in note.iter().flat_map(|n| n.lines()) {
+            println!("      {line}");
+        }
+    }
+    if verdict == Verdict::Re {
+        if let Some(reason) = execution.failure_reason() {
+            println!("      {reason}");
+        }
+        for line in execution.stderr_tail(STDERR_TAIL_LINES) {
+            println!("      | {line}");
+        }
+    }
+}
+
+/// Print the captured stderr of `execution` under `header`, if there is any.
+fn print_stderr(header: &str, execution: &Execution) {
+    if !execution.stderr.is_empty() {
+        println!("{header}");
+        print!("{}", execution.stderr);
+    }
+}
+
+/// Enumerate every `*.in` file in `testcase_dir`, sorted by name.
+fn collect_samples(testcase_dir: &Path) -> Result<Vec<PathBuf>> {
     if !testcase_dir.exists() {
         bail!("{} does not exist", testcase_dir.display());
     }
     // sample-*.in をすべて列挙
-    let mut samples: Vec<_> = fs::read_dir(&testcase_dir)?
+    let mut samples: Vec<_> = fs::read_dir(testcase_dir)?
         .filter_map(|entry| {
             let entry = entry.ok()?;
             let path = entry.path();
@@ -347,69 +2141,405 @@ fn debug_problem(contest: &str, problem: &str) -> Result<()> {
     if samples.is_empty() {
         bail!("No sample input files found in {}", testcase_dir.display());
     }
-    for sample_in in samples {
-        let stem = sample_in.file_stem().unwrap().to_string_lossy();
-        // sample-1.in → sample-1.out
-        let sample_out = testcase_dir.join(format!("{}.out", stem));
-        println!("==================== [{}] ====================", stem);
-        // 入力ファイル読み込み
-        println!("[input]");
-        let input_contents = fs::read_to_string(&sample_in)
-            .map(|c| c.trim_start_matches('\u{feff}').to_string())
-            .map_err(|e| anyhow::anyhow!("Failed to read sample input file '{}': {}", sample_in.display(), e))?;
-        println!("{}", input_contents);
+    Ok(samples)
+}
 
-        // debugビルド
-        println!("[debug output]");
-        let debug_output = run_cargo_bin(dir, problem, &input_contents, false)?;
-        println!("{}", debug_output);
+/// Replace the `sample-N` cases in `testcase_dir` with `samples`, leaving other cases alone.
+fn write_samples(testcase_dir: &Path, samples: &[atcoder::Sample]) -> Result<()> {
+    fs::create_dir_all(testcase_dir)?;
+    for entry in fs::read_dir(testcase_dir)? {
+        let path = entry?.path();
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let is_case = matches!(path.extension().and_then(OsStr::to_str), Some("in" | "out"));
+        if is_case && case_kind(&stem) == "sample" {
+            fs::remove_file(&path)?;
+        }
+    }
+    for (i, sample) in samples.iter().enumerate() {
+        let name = format!("sample-{}", i + 1);
+        fs::write(testcase_dir.join(format!("{name}.in")), &sample.input)?;
+        fs::write(testcase_dir.join(format!("{name}.out")), &sample.output)?;
+    }
+    Ok(())
+}
 
-        // releaseビルド
-        println!("[output]");
-        let start = std::time::Instant::now();
-        let release_output = run_cargo_bin(dir, problem, &input_contents, true)?;
-        let duration = start.elapsed();
-        println!("{}", release_output);
-        println!("Execution Time: {:?}", duration);
+/// Write `input`/`output` as the next free `<prefix>-N.in`/`.out` pair in `testcase_dir`.
+///
+/// Returns the path of the new input file.
+fn save_case(testcase_dir: &Path, prefix: &str, input: &str, output: &str) -> Result<PathBuf> {
+    fs::create_dir_all(testcase_dir)?;
+    let mut index = 1;
+    while testcase_dir.join(format!("{prefix}-{index}.in")).exists() {
+        index += 1;
+    }
+    let input_path = testcase_dir.join(format!("{prefix}-{index}.in"));
+    fs::write(&input_path, input)?;
+    fs::write(testcase_dir.join(format!("{prefix}-{index}.out")), output)?;
+    Ok(input_path)
+}
 
-        // 期待値
-        println!("[expect]");
-        let expected_output = fs::read_to_string(&sample_out)
-            .map(|c| {
-                let cleaned = c.trim_start_matches('\u{feff}').to_string();
-                println!("{}", cleaned);
-                cleaned
+/// Read a test case file, dropping a leading BOM.
+fn read_case_file(path: &Path) -> Result<String> {
+    fs::read_to_string(path)
+        .map(|c| c.trim_start_matches('\u{feff}').to_string())
+        .map_err(|e| anyhow::anyhow!("Failed to read test case file '{}': {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh scratch directory for test `name`.
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("kp-test-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn read(path: PathBuf) -> String {
+        fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn import_takes_each_sample_once_and_keeps_other_cases() {
+        let dir = scratch_dir("import");
+        let cases = dir.join("testcases").join("a");
+        fs::create_dir_all(&cases).unwrap();
+        for name in ["sample-5", "custom-1", "stress-1"] {
+            fs::write(cases.join(format!("{name}.in")), "old\n").unwrap();
+            fs::write(cases.join(format!("{name}.out")), "old\n").unwrap();
+        }
+        fs::write(cases.join("checker.rs"), "fn main() {}\n").unwrap();
+
+        let page = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/atcoder/task.html");
+        import_samples(&dir, "a", &page).unwrap();
+
+        let mut names: Vec<String> = fs::read_dir(&cases)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "checker.rs",
+                "custom-1.in",
+                "custom-1.out",
+                "sample-1.in",
+                "sample-1.out",
+                "sample-2.in",
+                "sample-2.out",
+                "sample-3.in",
+                "sample-3.out",
+                "stress-1.in",
+                "stress-1.out",
+            ]
+        );
+        assert_eq!(read(cases.join("sample-1.in")), "1 2\n");
+        assert_eq!(read(cases.join("sample-3.out")), "はい\n");
+        assert_eq!(read(cases.join("custom-1.in")), "old\n");
+        assert_eq!(read(cases.join("stress-1.out")), "old\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn import_fails_without_samples() {
+        let dir = scratch_dir("import-empty");
+        let page = dir.join("page.html");
+        fs::write(
+            &page,
+            "<html><body><h3>問題文</h3><p>no samples</p></body></html>",
+        )
+        .unwrap();
+        assert!(import_samples(&dir, "a", &page).is_err());
+        assert!(!dir.join("testcases").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn write_samples_replaces_only_samples() {
+        let dir = scratch_dir("write-samples");
+        let cases = dir.join("testcases").join("b");
+        fs::create_dir_all(&cases).unwrap();
+        for name in ["sample-1", "sample-2", "custom-2", "stress-3"] {
+            fs::write(cases.join(format!("{name}.in")), "old\n").unwrap();
+            fs::write(cases.join(format!("{name}.out")), "old\n").unwrap();
+        }
+        let samples = [atcoder::Sample {
+            input: "1\n".to_string(),
+            output: "2\n".to_string(),
+        }];
+        write_samples(&cases, &samples).unwrap();
+
+        assert_eq!(read(cases.join("sample-1.in")), "1\n");
+        assert_eq!(read(cases.join("sample-1.out")), "2\n");
+        assert!(!cases.join("sample-2.in").exists());
+        assert!(!cases.join("sample-2.out").exists());
+        assert_eq!(read(cases.join("custom-2.in")), "old\n");
+        assert_eq!(read(cases.join("stress-3.in")), "old\n");
+        assert_eq!(read(cases.join("stress-3.out")), "old\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// A workspace of contest `id` in the directory `name` under a fresh scratch directory
+    /// `scratch`, with one task per `(label, task directory, submit file)`.
+    fn workspace(scratch: &str, name: &str, id: &str, tasks: &[(&str, &str, &str)]) -> PathBuf {
+        let dir = scratch_dir(scratch).join(name);
+        let tasks: Vec<_> = tasks
+            .iter()
+            .map(|(label, path, submit)| {
+                let source = dir.join(submit);
+                fs::create_dir_all(source.parent().unwrap()).unwrap();
+                fs::write(source, "fn main() {}\n").unwrap();
+                json!({ "label": label, "directory": { "path": path, "submit": submit } })
             })
-            .map_err(|e| anyhow::anyhow!("Failed to read expected output file '{}': {}", sample_out.display(), e))?;
+            .collect();
+        let contest_json = json!({ "contest": { "id": id }, "tasks": tasks });
+        fs::write(dir.join("contest.acc.json"), contest_json.to_string()).unwrap();
+        fs::canonicalize(dir).unwrap()
+    }
 
-        // 比較
-        println!("[comparison result]");
-        if release_output.trim() == expected_output.trim() {
-            println!("[✅ Complete] Output matches expected output.");
-        } else {
-            println!("[❌ Failed] Output does not match expected output.");
+    fn target(contest: Option<&str>, problem: Option<&str>) -> TargetArgs {
+        TargetArgs {
+            contest: contest.map(str::to_string),
+            problem: problem.map(str::to_string),
         }
-        println!("");
     }
-    Ok(())
-}
 
-fn run_cargo_bin(dir: &Path, problem: &str, input: &str, release: bool) -> Result<String> {
-    use std::process::{Command, Stdio};
-    let mut cmd = Command::new("cargo");
-    cmd.current_dir(dir)
-        .arg("run")
-        .arg("--bin").arg(problem);
-    if release {
-        cmd.arg("--release");
+    const BINS: [(&str, &str, &str); 3] = [
+        ("A", "./", "src/bin/a.rs"),
+        ("B", "./", "src/bin/b.rs"),
+        ("C", "./", "src/bin/c.rs"),
+    ];
+
+    #[test]
+    fn locate_takes_a_single_task_as_the_problem() {
+        let dir = workspace("locate-problem", "abc300", "abc300", &BINS);
+        let bin_dir = dir.join("src").join("bin");
+        let locate = |args: TargetArgs, cwd: &Path| args.locate_from(cwd).unwrap();
+        assert_eq!(locate(target(None, None), &bin_dir), (dir.clone(), None));
+        assert_eq!(
+            locate(target(Some("b"), None), &dir),
+            (dir.clone(), Some("b".to_string()))
+        );
+        assert_eq!(
+            locate(target(Some("C"), None), &bin_dir),
+            (dir.clone(), Some("c".to_string()))
+        );
+        let outside = dir.parent().unwrap();
+        let err = target(None, None).locate_from(outside).err().unwrap();
+        assert!(err
+            .to_string()
+            .starts_with("not inside a contest workspace"));
+        fs::remove_dir_all(outside).unwrap();
     }
-    cmd.stdin(Stdio::piped()).stdout(Stdio::piped());
-    let mut child = cmd.spawn().with_context(|| format!("Failed to spawn cargo run for bin {}", problem))?;
-    {
-        let stdin = child.stdin.as_mut().expect("Failed to open stdin");
-        use std::io::Write;
-        stdin.write_all(input.as_bytes())?;
+
+    #[test]
+    fn locate_resolves_the_workspace_by_its_own_contest_id() {
+        // The directory is named differently from the contest.
+        let dir = workspace("locate-contest", "practice", "abc300", &BINS);
+        let bin_dir = dir.join("src").join("bin");
+        for contest in ["abc300", "ABC300", "practice"] {
+            assert_eq!(
+                target(Some(contest), Some("a"))
+                    .locate_from(&bin_dir)
+                    .unwrap(),
+                (dir.clone(), Some("a".to_string())),
+                "{contest}"
+            );
+            assert_eq!(
+                target(Some(contest), None).locate_from(&dir).unwrap(),
+                (dir.clone(), None),
+                "{contest}"
+            );
+        }
+        // Any other contest is a path, as seen from the current directory.
+        let err = target(Some("abc301"), Some("a"))
+            .locate_from(&dir)
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "abc301 does not exist");
+        let outside = dir.parent().unwrap();
+        assert_eq!(
+            target(Some("practice"), Some("a"))
+                .locate_from(outside)
+                .unwrap(),
+            (PathBuf::from("practice"), Some("a".to_string()))
+        );
+        fs::remove_dir_all(outside).unwrap();
+    }
+
+    #[test]
+    fn infer_problem_takes_the_newest_submit_file() {
+        let dir = workspace("infer-newest", "abc300", "abc300", &BINS);
+        let bin_dir = dir.join("src").join("bin");
+        let touch = |name: &str, age: u64| {
+            let time = std::time::SystemTime::now() - Duration::from_secs(age);
+            let file = fs::File::options()
+                .write(true)
+                .open(bin_dir.join(name))
+                .unwrap();
+            file.set_modified(time).unwrap();
+        };
+        touch("a.rs", 300);
+        touch("b.rs", 100);
+        touch("c.rs", 200);
+        assert_eq!(infer_problem(&dir, &bin_dir).unwrap(), "b");
+        touch("c.rs", 0);
+        assert_eq!(infer_problem(&dir, &dir).unwrap(), "c");
+        fs::remove_dir_all(dir.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn infer_problem_takes_the_task_directory_the_cwd_is_in() {
+        let dir = workspace(
+            "infer-directory",
+            "abc300",
+            "abc300",
+            &[("A", "a", "a/main.rs"), ("B", "b", "b/main.rs")],
+        );
+        let touch = |path: &str| {
+            let file = fs::File::options()
+                .write(true)
+                .open(dir.join(path))
+                .unwrap();
+            file.set_modified(std::time::SystemTime::now()).unwrap();
+        };
+        touch("b/main.rs");
+        assert_eq!(infer_problem(&dir, &dir.join("a")).unwrap(), "a");
+        assert_eq!(infer_problem(&dir, &dir).unwrap(), "b");
+        fs::remove_dir_all(dir.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn case_kind_by_name() {
+        assert_eq!(case_kind("sample-1"), "sample");
+        assert_eq!(case_kind("custom-12"), "custom");
+        assert_eq!(case_kind("stress-3"), "stress");
+        assert_eq!(case_kind("big"), "other");
+        assert_eq!(case_kind("my-sample-1"), "other");
+    }
+
+    #[test]
+    fn save_case_numbers_after_the_existing_cases() {
+        let dir = scratch_dir("save-case");
+        let cases = dir.join("testcases").join("a");
+        let first = save_case(&cases, "custom", "1\n", "2\n").unwrap();
+        assert_eq!(first, cases.join("custom-1.in"));
+        assert_eq!(read(cases.join("custom-1.out")), "2\n");
+        assert_eq!(
+            save_case(&cases, "custom", "3\n", "4\n").unwrap(),
+            cases.join("custom-2.in")
+        );
+        // Other kinds are numbered on their own, and a gap is filled.
+        assert_eq!(
+            save_case(&cases, "stress", "5\n", "6\n").unwrap(),
+            cases.join("stress-1.in")
+        );
+        fs::remove_file(&first).unwrap();
+        assert_eq!(
+            save_case(&cases, "custom", "7\n", "8\n").unwrap(),
+            cases.join("custom-1.in")
+        );
+        assert_eq!(read(cases.join("custom-1.in")), "7\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn case_paths_take_a_number_as_a_custom_case() {
+        let dir = scratch_dir("case-paths");
+        let cases = dir.join("testcases").join("a");
+        save_case(&cases, "custom", "1\n", "2\n").unwrap();
+        save_case(&cases, "sample", "1\n", "2\n").unwrap();
+        let custom = (cases.join("custom-1.in"), cases.join("custom-1.out"));
+        assert_eq!(case_paths(&dir, "a", "1").unwrap(), custom);
+        assert_eq!(case_paths(&dir, "a", "custom-1").unwrap(), custom);
+        assert_eq!(case_paths(&dir, "a", "custom-1.out").unwrap(), custom);
+        assert_eq!(
+            case_paths(&dir, "a", "sample-1.in").unwrap(),
+            (cases.join("sample-1.in"), cases.join("sample-1.out"))
+        );
+        let err = case_paths(&dir, "a", "2").err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            format!("no case named custom-2 in {}", cases.display())
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn remove_case_keeps_samples_unless_forced() {
+        let dir = scratch_dir("remove-case");
+        let cases = dir.join("testcases").join("a");
+        save_case(&cases, "sample", "1\n", "2\n").unwrap();
+        save_case(&cases, "custom", "1\n", "2\n").unwrap();
+
+        let err = remove_case(&dir, "a", "sample-1", false).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "sample-1 is a downloaded sample; pass --force to delete it anyway"
+        );
+        assert!(cases.join("sample-1.in").exists());
+        remove_case(&dir, "a", "1", false).unwrap();
+        assert!(!cases.join("custom-1.in").exists());
+        assert!(!cases.join("custom-1.out").exists());
+        remove_case(&dir, "a", "sample-1", true).unwrap();
+        assert!(!cases.join("sample-1.in").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn contest_summary_marks_a_bin_that_fails_to_compile() {
+        let dir = workspace("contest-summary", "abc300", "abc300", &BINS);
+        fs::write(
+            dir.join("Cargo.toml"),
+            "[package]\nname = \"abc300\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n\
+             [workspace]\n",
+        )
+        .unwrap();
+        let bin_dir = dir.join("src").join("bin");
+        fs::write(bin_dir.join("a.rs"), "fn main() { println!(\"1\"); }\n").unwrap();
+        fs::write(bin_dir.join("b.rs"), "fn main() { let }\n").unwrap();
+        let cases = dir.join("testcases").join("a");
+        fs::create_dir_all(&cases).unwrap();
+        for (name, answer) in [("sample-1", "1\n"), ("sample-2", "2\n")] {
+            fs::write(cases.join(format!("{name}.in")), "").unwrap();
+            fs::write(cases.join(format!("{name}.out")), answer).unwrap();
+        }
+        fs::create_dir_all(dir.join("testcases").join("b")).unwrap();
+
+        let rows = judge_contest(&dir, 1, &JudgeArgs::default()).unwrap();
+        let (table, all_passed) = summary_table(&rows);
+        assert_eq!(
+            table,
+            [
+                "Problem   Passed  Failures",
+                "A            1/2  ❌ WA sample-2",
+                "B              -  ❌ CE",
+                "C              -  (no test cases)",
+            ]
+        );
+        assert!(!all_passed);
+        fs::remove_dir_all(dir.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn summary_describes_the_failing_cases() {
+        let mut summary = TestSummary {
+            passed: 3,
+            total: 3,
+            failures: Vec::new(),
+        };
+        assert_eq!(summary.describe(), "✅ 3/3 passed");
+        summary.passed = 1;
+        summary.failures = vec![
+            ("sample-2".to_string(), Verdict::Wa),
+            ("custom-1".to_string(), Verdict::Tle),
+        ];
+        assert_eq!(
+            summary.describe(),
+            "❌ 1/3 passed: WA sample-2, TLE custom-1"
+        );
     }
-    let output = child.wait_with_output()?;
-    Ok(String::from_utf8_lossy(&output.stdout).to_string())
 }
@@ -0,0 +1,820 @@
+//! Building solutions with cargo and executing the produced binaries.
+
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+
+use crate::{manifest_bin_path, register_bin};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Result of running a solution once.
+pub struct Execution {
+    pub stdout: String,
+    pub stderr: String,
+    pub status: ExitStatus,
+    pub elapsed: Duration,
+    /// The process was killed for exceeding the time limit.
+    pub timed_out: bool,
+    /// Peak resident set size in bytes, where the platform can report it. This is an upper
+    /// bound: it includes the memory the child inherited from kp at fork.
+    pub peak_memory: Option<u64>,
+}
+
+impl Execution {
+    /// Describe why the process failed (signal, panic, stack overflow, exit code),
+    /// or `None` when it exited successfully.
+    pub fn failure_reason(&self) -> Option<String> {
+        describe_failure(&self.status, &self.stderr)
+    }
+
+    /// Last `n` lines of stderr.
+    pub fn stderr_tail(&self, n: usize) -> Vec<&str> {
+        let lines: Vec<&str> = self.stderr.lines().collect();
+        lines[lines.len().saturating_sub(n)..].to_vec()
+    }
+}
+
+/// Describe why a process with `status` and `stderr` failed (signal, panic, stack overflow,
+/// exit code), or `None` when it exited successfully.
+pub fn describe_failure(status: &ExitStatus, stderr: &str) -> Option<String> {
+    if status.success() {
+        return None;
+    }
+    let mut reason = match signal_name(status) {
+        Some(signal) => format!("killed by {signal}"),
+        None => match status.code() {
+            Some(code) => format!("exit code {code}"),
+            None => status.to_string(),
+        },
+    };
+    if stderr.contains("has overflowed its stack") {
+        reason.push_str(" (stack overflow)");
+    } else if let Some(panic) = panic_message(stderr) {
+        reason.push_str(&format!(" ({panic})"));
+    }
+    Some(reason)
+}
+
+/// Extract the panic location and message from stderr.
+///
+/// Handles both `panicked at src/main.rs:1:2:\n<msg>` and the older
+/// `panicked at '<msg>', src/main.rs:1:2` formats.
+fn panic_message(stderr: &str) -> Option<String> {
+    let mut lines = stderr.lines();
+    let header = lines.find(|l| l.contains("panicked at "))?;
+    let at = &header[header.find("panicked at ")? + "panicked at ".len()..];
+    if at.starts_with('\'') {
+        return Some(format!("panicked at {at}"));
+    }
+    let message = lines.next().unwrap_or_default();
+    Some(
+        format!("panicked at {} {}", at, message)
+            .trim_end()
+            .to_string(),
+    )
+}
+
+/// Name of the signal that terminated the process, if any.
+#[cfg(unix)]
+fn signal_name(status: &ExitStatus) -> Option<String> {
+    use std::os::unix::process::ExitStatusExt;
+    let signal = status.signal()?;
+    let name = match signal {
+        libc::SIGSEGV => "SIGSEGV",
+        libc::SIGABRT => "SIGABRT",
+        libc::SIGFPE => "SIGFPE",
+        libc::SIGBUS => "SIGBUS",
+        libc::SIGILL => "SIGILL",
+        libc::SIGKILL => "SIGKILL",
+        libc::SIGTERM => "SIGTERM",
+        libc::SIGPIPE => "SIGPIPE",
+        libc::SIGTRAP => "SIGTRAP",
+        libc::SIGSYS => "SIGSYS",
+        libc::SIGXCPU => "SIGXCPU",
+        _ => return Some(format!("signal {signal}")),
+    };
+    Some(name.to_string())
+}
+
+/// Name of the signal that terminated the process, if any.
+#[cfg(not(unix))]
+fn signal_name(_status: &ExitStatus) -> Option<String> {
+    None
+}
+
+/// Run `cargo build --bin <name>` in `dir` and return the path of the built executable.
+///
+/// Compiler diagnostics are forwarded to stderr as cargo would print them.
+pub fn build_bin(dir: &Path, name: &str, release: bool) -> Result<PathBuf> {
+    let mut executables = build_bins(dir, &[name.to_string()], release)?;
+    executables
+        .remove(name)
+        .ok_or_else(|| anyhow::anyhow!("`cargo build` of {} failed", name))
+}
+
+/// Build several bins with a single cargo invocation, returning their executables by name.
+///
+/// Cargo keeps going past a bin that does not compile, so the others are still built; the
+/// ones that failed are missing from the result.
+pub fn build_bins(dir: &Path, names: &[String], release: bool) -> Result<HashMap<String, PathBuf>> {
+    let mut cmd = Command::new("cargo");
+    cmd.current_dir(dir).arg("build");
+    for name in names {
+        cmd.args(["--bin", name]);
+    }
+    cmd.arg("--keep-going");
+    // Diagnostics are colored under the same conditions as our own output.
+    cmd.arg(if crate::compare::use_color() {
+        "--message-format=json-diagnostic-rendered-ansi"
+    } else {
+        "--message-format=json"
+    });
+    if release {
+        cmd.arg("--release");
+    }
+    cmd.stdout(Stdio::piped());
+    let mut child = cmd
+        .spawn()
+        .with_context(|| format!("Failed to spawn cargo build for {}", names.join(", ")))?;
+
+    let mut executables = HashMap::new();
+    let stdout = child.stdout.take().expect("Failed to open stdout");
+    for line in BufReader::new(stdout).lines() {
+        let message: Value = match serde_json::from_str(&line?) {
+            Ok(v) => v,
+            Err(_) => continue,
+        };
+        match message["reason"].as_str() {
+            Some("compiler-message") => {
+                if let Some(rendered) = message["message"]["rendered"].as_str() {
+                    eprint!("{}", rendered);
+                }
+            }
+            Some("compiler-artifact") => {
+                if let (Some(name), Some(path)) = (
+                    message["target"]["name"].as_str(),
+                    message["executable"].as_str(),
+                ) {
+                    executables.insert(name.to_string(), PathBuf::from(path));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let status = child.wait()?;
+    if !status.success() && executables.is_empty() {
+        bail!(
+            "`cargo build` of {} failed with status {}",
+            names.join(", "),
+            status
+        );
+    }
+    Ok(executables)
+}
+
+/// Locate and build a helper program (checker, judge, …) belonging to `problem`.
+///
+/// `spec` is either a path to an executable or the name of a bin in the contest manifest.
+/// Without it, `testcases/<problem>/<role>.rs` is registered as the bin `<problem>_<role>`
+/// if that file exists.
+pub fn resolve_problem_bin(
+    dir: &Path,
+    problem: &str,
+    role: &str,
+    spec: Option<&str>,
+) -> Result<Option<PathBuf>> {
+    match spec {
+        Some(spec) if Path::new(spec).is_file() => fs::canonicalize(spec)
+            .map(Some)
+            .with_context(|| format!("cannot resolve {} {}", role, spec)),
+        Some(bin) => build_bin(dir, bin, true).map(Some),
+        None => {
+            let source = dir
+                .join("testcases")
+                .join(problem)
+                .join(format!("{role}.rs"));
+            if !source.exists() {
+                return Ok(None);
+            }
+            let name = format!("{problem}_{role}");
+            register_bin(dir, &name, &format!("testcases/{problem}/{role}.rs"))?;
+            build_bin(dir, &name, true).map(Some)
+        }
+    }
+}
+
+/// Make sure the contest in `dir` has a bin called `name`, registering `<name>.rs` from the
+/// directory holding `problem`'s source when it is not in the manifest yet.
+pub fn ensure_sibling_bin(dir: &Path, problem: &str, name: &str) -> Result<()> {
+    if manifest_bin_path(dir, name)?.is_some() {
+        return Ok(());
+    }
+    let problem_path = manifest_bin_path(dir, problem)?.ok_or_else(|| {
+        anyhow::anyhow!("bin `{}` is not in {}/Cargo.toml", problem, dir.display())
+    })?;
+    let path = match Path::new(&problem_path).parent() {
+        Some(parent) if parent != Path::new("") => {
+            format!(
+                "{}/{}.rs",
+                parent.to_string_lossy().replace('\\', "/"),
+                name
+            )
+        }
+        _ => format!("{name}.rs"),
+    };
+    if !dir.join(&path).exists() {
+        bail!(
+            "bin `{}` not found: add it to Cargo.toml or create {}",
+            name,
+            dir.join(&path).display()
+        );
+    }
+    register_bin(dir, name, &path)
+}
+
+/// Execute `exe` in `dir`, feeding `input` to its stdin.
+///
+/// When `time_limit` is given, the process tree is killed once it runs longer than that
+/// and the execution is reported as timed out.
+pub fn run_binary(
+    dir: &Path,
+    exe: &Path,
+    input: &str,
+    time_limit: Option<Duration>,
+) -> Result<Execution> {
+    run_binary_with_args(dir, exe, &[], input, time_limit)
+}
+
+/// Like [`run_binary`], passing `args` on the command line.
+pub fn run_binary_with_args(
+    dir: &Path,
+    exe: &Path,
+    args: &[String],
+    input: &str,
+    time_limit: Option<Duration>,
+) -> Result<Execution> {
+    let mut cmd = Command::new(exe);
+    cmd.current_dir(dir)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    isolate(&mut cmd);
+    let start = Instant::now();
+    let mut child = cmd
+        .spawn()
+        .with_context(|| format!("Failed to spawn {}", exe.display()))?;
+
+    // Feed stdin and drain stdout/stderr from other threads so a large output can't deadlock us.
+    let mut stdin = child.stdin.take().expect("Failed to open stdin");
+    let input = input.to_owned();
+    let writer = thread::spawn(move || {
+        // The solution may exit without reading everything; that is not our error.
+        let _ = stdin.write_all(input.as_bytes());
+    });
+    let stdout_reader = drain(child.stdout.take().expect("Failed to open stdout"));
+    let stderr_reader = drain(child.stderr.take().expect("Failed to open stderr"));
+
+    let (status, peak_memory, timed_out) = wait_with_limit(&mut child, start, time_limit)?;
+    let elapsed = start.elapsed();
+    let _ = writer.join();
+    let stdout = stdout_reader.join().unwrap_or_default();
+    let stderr = stderr_reader.join().unwrap_or_default();
+
+    Ok(Execution {
+        stdout: String::from_utf8_lossy(&stdout).to_string(),
+        stderr: String::from_utf8_lossy(&stderr).to_string(),
+        status,
+        elapsed,
+        timed_out,
+        peak_memory,
+    })
+}
+
+/// Run `exe` attached to the terminal: stdout goes straight through, stdin is `input` or
+/// the terminal, and stderr is shown as it comes while also being kept for the report.
+///
+/// The returned `stdout` is always empty. The process keeps our process group so that it
+/// may read from the terminal; hence there is no time limit either.
+pub fn run_binary_live(dir: &Path, exe: &Path, input: Stdio) -> Result<Execution> {
+    let mut cmd = Command::new(exe);
+    cmd.current_dir(dir)
+        .stdin(input)
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::piped());
+    let start = Instant::now();
+    let mut child = cmd
+        .spawn()
+        .with_context(|| format!("Failed to spawn {}", exe.display()))?;
+
+    let mut pipe = child.stderr.take().expect("Failed to open stderr");
+    let stderr_reader = thread::spawn(move || {
+        let mut kept = Vec::new();
+        let mut buf = [0; 4096];
+        while let Ok(n @ 1..) = pipe.read(&mut buf) {
+            let _ = std::io::stderr().write_all(&buf[..n]);
+            kept.extend_from_slice(&buf[..n]);
+        }
+        kept
+    });
+
+    let (status, peak_memory, timed_out) = wait_with_limit(&mut child, start, None)?;
+    let elapsed = start.elapsed();
+    let stderr = stderr_reader.join().unwrap_or_default();
+
+    Ok(Execution {
+        stdout: String::new(),
+        stderr: String::from_utf8_lossy(&stderr).to_string(),
+        status,
+        elapsed,
+        timed_out,
+        peak_memory,
+    })
+}
+
+/// Put the process in its own process group so that a timeout can kill everything it spawned.
+pub fn isolate(cmd: &mut Command) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+    #[cfg(not(unix))]
+    let _ = cmd;
+}
+
+/// Read `pipe` to the end on a background thread.
+pub fn drain(mut pipe: impl Read + Send + 'static) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = pipe.read_to_end(&mut buf);
+        buf
+    })
+}
+
+/// How often a running solution is checked against its time limit.
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Wait for `child` to exit, killing it once `time_limit` has passed since `start`.
+///
+/// Returns the exit status, the peak memory usage and whether the child was killed.
+#[cfg(unix)]
+pub fn wait_with_limit(
+    child: &mut Child,
+    start: Instant,
+    time_limit: Option<Duration>,
+) -> Result<(ExitStatus, Option<u64>, bool)> {
+    use std::os::unix::process::ExitStatusExt;
+
+    // Reap the child ourselves with wait4(2) so that its resource usage comes along.
+    let pid = child.id() as libc::pid_t;
+    let mut raw_status = 0;
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    let mut timed_out = false;
+    loop {
+        let flags = if timed_out { 0 } else { libc::WNOHANG };
+        let ret = unsafe { libc::wait4(pid, &mut raw_status, flags, &mut usage) };
+        if ret == pid {
+            break;
+        }
+        if ret < 0 {
+            let err = std::io::Error::last_os_error();
+            if err.kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(err).context("wait4 failed");
+        }
+        if time_limit.is_some_and(|tl| start.elapsed() > tl) {
+            kill_tree(child);
+            timed_out = true;
+        } else {
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    // ru_maxrss is in kilobytes on Linux but in bytes on macOS.
+    let max_rss = usage.ru_maxrss.max(0) as u64;
+    let peak_memory = if cfg!(target_os = "macos") {
+        max_rss
+    } else {
+        max_rss * 1024
+    };
+    Ok((
+        ExitStatus::from_raw(raw_status),
+        Some(peak_memory),
+        timed_out,
+    ))
+}
+
+/// Wait for `child` to exit, killing it once `time_limit` has passed since `start`.
+///
+/// Returns the exit status, the peak memory usage and whether the child was killed.
+#[cfg(not(unix))]
+pub fn wait_with_limit(
+    child: &mut Child,
+    start: Instant,
+    time_limit: Option<Duration>,
+) -> Result<(ExitStatus, Option<u64>, bool)> {
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok((status, None, false));
+        }
+        if time_limit.is_some_and(|tl| start.elapsed() > tl) {
+            kill_tree(child);
+            return Ok((child.wait()?, None, true));
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Kill `child` together with every process it spawned.
+fn kill_tree(child: &mut Child) {
+    kill_process_tree(child.id());
+    let _ = child.kill();
+}
+
+/// Kill the process `pid` together with every process it spawned.
+#[cfg(unix)]
+pub fn kill_process_tree(pid: u32) {
+    // The process leads its own process group (see `isolate`), so signal the whole group.
+    unsafe {
+        libc::kill(-(pid as libc::pid_t), libc::SIGKILL);
+    }
+}
+
+/// Kill the process `pid` together with every process it spawned.
+#[cfg(windows)]
+pub fn kill_process_tree(pid: u32) {
+    let _ = Command::new("taskkill")
+        .args(["/T", "/F", "/PID", &pid.to_string()])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+}
+
+/// Run `work` on every item using up to `jobs` threads (0 = one per CPU core) and pass
+/// the results to `report` in the order of `items`, each as soon as it and everything
+/// before it are done.
+///
+/// Stops handing out new items once `report` fails, and returns that error.
+pub fn run_ordered<T, R>(
+    items: &[T],
+    jobs: usize,
+    work: impl Fn(&T) -> R + Sync,
+    mut report: impl FnMut(R) -> Result<()>,
+) -> Result<()>
+where
+    T: Sync,
+    R: Send,
+{
+    let jobs = match jobs {
+        0 => thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    }
+    .clamp(1, items.len().max(1));
+    let next = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let (tx, rx) = mpsc::channel();
+
+    thread::scope(|scope| {
+        for _ in 0..jobs {
+            let tx = tx.clone();
+            let (next, stop, work) = (&next, &stop, &work);
+            scope.spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    if i >= items.len() || tx.send((i, work(&items[i]))).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(tx);
+
+        let mut done: Vec<Option<R>> = items.iter().map(|_| None).collect();
+        let mut cursor = 0;
+        for (i, result) in rx {
+            done[i] = Some(result);
+            while let Some(result) = done.get_mut(cursor).and_then(Option::take) {
+                cursor += 1;
+                if let Err(e) = report(result) {
+                    stop.store(true, Ordering::Relaxed);
+                    return Err(e);
+                }
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Parse a duration such as `2s`, `1.5s`, `500ms` or a bare number of seconds.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let (number, scale) = if let Some(ms) = s.strip_suffix("ms") {
+        (ms, 1e-3)
+    } else if let Some(sec) = s.strip_suffix("sec").or_else(|| s.strip_suffix('s')) {
+        (sec, 1.0)
+    } else {
+        (s, 1.0)
+    };
+    let value: f64 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid duration `{s}` (expected e.g. `2s` or `500ms`)"))?;
+    if !value.is_finite() || value <= 0.0 {
+        return Err(format!("duration must be positive: `{s}`"));
+    }
+    Ok(Duration::from_secs_f64(value * scale))
+}
+
+/// Parse a memory size such as `1024MiB`, `256MB`, `512K` or a bare number of MiB.
+pub fn parse_memory(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let scale: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "b" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "" | "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        _ => {
+            return Err(format!(
+                "invalid memory size `{s}` (expected e.g. `1024MiB`)"
+            ))
+        }
+    };
+    let value: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid memory size `{s}` (expected e.g. `1024MiB`)"))?;
+    if value <= 0.0 {
+        return Err(format!("memory size must be positive: `{s}`"));
+    }
+    Ok((value * scale as f64) as u64)
+}
+
+/// Format a byte count as MiB for display.
+pub fn format_memory(bytes: u64) -> String {
+    format!("{:.1} MiB", bytes as f64 / (1 << 20) as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn run_ordered_reports_in_item_order() {
+        // Later items finish first, so the results arrive in reverse.
+        let items: Vec<u64> = (0..6).collect();
+        let finished = Mutex::new(Vec::new());
+        let mut reported = Vec::new();
+        run_ordered(
+            &items,
+            items.len(),
+            |&i| {
+                thread::sleep(Duration::from_millis(20 * (6 - i)));
+                finished.lock().unwrap().push(i);
+                i
+            },
+            |i| {
+                reported.push(i);
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(reported, items);
+        assert_ne!(finished.into_inner().unwrap(), items);
+    }
+
+    #[test]
+    fn run_ordered_stops_after_an_error() {
+        let items: Vec<u64> = (0..100).collect();
+        let started = AtomicUsize::new(0);
+        let mut reported = Vec::new();
+        let result = run_ordered(
+            &items,
+            2,
+            |&i| {
+                started.fetch_add(1, Ordering::Relaxed);
+                thread::sleep(Duration::from_millis(5));
+                i
+            },
+            |i| {
+                reported.push(i);
+                if i == 2 {
+                    bail!("failed at {i}");
+                }
+                Ok(())
+            },
+        );
+        assert_eq!(result.unwrap_err().to_string(), "failed at 2");
+        assert_eq!(reported, [0, 1, 2]);
+        // The workers only finish what they were running.
+        assert!(started.into_inner() < items.len());
+    }
+
+    #[test]
+    fn run_ordered_handles_no_items() {
+        let mut calls = 0;
+        run_ordered(
+            &[] as &[u64],
+            0,
+            |&i| i,
+            |_| {
+                calls += 1;
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(calls, 0);
+    }
+
+    #[test]
+    fn parse_duration_units() {
+        assert_eq!(parse_duration("2s"), Ok(Duration::from_secs(2)));
+        assert_eq!(parse_duration("1.5s"), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("2sec"), Ok(Duration::from_secs(2)));
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration(" 3 "), Ok(Duration::from_secs(3)));
+        assert_eq!(parse_duration("250 ms"), Ok(Duration::from_millis(250)));
+    }
+
+    #[test]
+    fn parse_duration_rejects_bad_input() {
+        for bad in ["", "abc", "2m", "s", "1h", "inf", "nan"] {
+            assert!(parse_duration(bad).is_err(), "{bad}");
+        }
+        for not_positive in ["0", "0ms", "-1s"] {
+            assert_eq!(
+                parse_duration(not_positive),
+                Err(format!("duration must be positive: `{not_positive}`"))
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn wait_with_limit_kills_a_sleeping_child() {
+        let mut cmd = Command::new("sleep");
+        cmd.arg("10");
+        isolate(&mut cmd);
+        let start = Instant::now();
+        let mut child = cmd.spawn().unwrap();
+        let (status, _, timed_out) =
+            wait_with_limit(&mut child, start, Some(Duration::from_millis(100))).unwrap();
+        assert!(timed_out);
+        assert_eq!(signal_name(&status).as_deref(), Some("SIGKILL"));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn timeout_kills_the_whole_process_tree() {
+        // The background sleep keeps stdout open; if it survived, reading the output would
+        // wait for it to finish.
+        let args = ["-c".to_string(), "sleep 10 & sleep 10".to_string()];
+        let start = Instant::now();
+        let execution = run_binary_with_args(
+            &std::env::temp_dir(),
+            Path::new("sh"),
+            &args,
+            "",
+            Some(Duration::from_millis(100)),
+        )
+        .unwrap();
+        assert!(execution.timed_out);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn a_child_within_the_limit_is_not_killed() {
+        let mut child = Command::new("cargo")
+            .arg("--version")
+            .stdout(Stdio::null())
+            .spawn()
+            .unwrap();
+        let (status, _, timed_out) =
+            wait_with_limit(&mut child, Instant::now(), Some(Duration::from_secs(60))).unwrap();
+        assert!(status.success());
+        assert!(!timed_out);
+    }
+
+    #[test]
+    fn parse_memory_units() {
+        assert_eq!(parse_memory("1024MiB"), Ok(1 << 30));
+        assert_eq!(parse_memory("256MB"), Ok(256 << 20));
+        assert_eq!(parse_memory("256m"), Ok(256 << 20));
+        assert_eq!(parse_memory("512K"), Ok(512 << 10));
+        assert_eq!(parse_memory("2GiB"), Ok(2 << 30));
+        assert_eq!(parse_memory("1.5g"), Ok(3 << 29));
+        assert_eq!(parse_memory("100b"), Ok(100));
+        assert_eq!(parse_memory("64"), Ok(64 << 20));
+        assert_eq!(parse_memory(" 64 MiB "), Ok(64 << 20));
+    }
+
+    #[test]
+    fn parse_memory_rejects_bad_input() {
+        for bad in ["", "MiB", "abc", "12TB", "1..5M", "-1M"] {
+            assert!(parse_memory(bad).is_err(), "{bad}");
+        }
+        assert_eq!(
+            parse_memory("0MiB"),
+            Err("memory size must be positive: `0MiB`".to_string())
+        );
+    }
+
+    #[test]
+    fn format_memory_in_mib() {
+        assert_eq!(format_memory(0), "0.0 MiB");
+        assert_eq!(format_memory(1536 << 10), "1.5 MiB");
+        assert_eq!(format_memory(1 << 30), "1024.0 MiB");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn peak_memory_is_measured() {
+        let execution = run_binary(&std::env::temp_dir(), Path::new("true"), "", None).unwrap();
+        assert!(execution.peak_memory.is_some_and(|bytes| bytes > 0));
+    }
+
+    #[test]
+    fn panic_message_in_the_current_format() {
+        let stderr = "thread 'main' panicked at src/bin/a.rs:3:5:\n\
+                      attempt to add with overflow\n\
+                      note: run with `RUST_BACKTRACE=1` to display a backtrace\n";
+        assert_eq!(
+            panic_message(stderr).as_deref(),
+            Some("panicked at src/bin/a.rs:3:5: attempt to add with overflow")
+        );
+    }
+
+    #[test]
+    fn panic_message_in_the_old_format() {
+        let stderr = "thread 'main' panicked at 'attempt to divide by zero', src/bin/a.rs:7:13\n\
+                      note: run with `RUST_BACKTRACE=1` to display a backtrace\n";
+        assert_eq!(
+            panic_message(stderr).as_deref(),
+            Some("panicked at 'attempt to divide by zero', src/bin/a.rs:7:13")
+        );
+        assert_eq!(panic_message("Segmentation fault\n"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn describe_failure_reports_signals_and_panics() {
+        use std::os::unix::process::ExitStatusExt;
+        assert_eq!(
+            describe_failure(&ExitStatus::from_raw(0), "panicked at x"),
+            None
+        );
+
+        let stderr =
+            "\nthread 'main' has overflowed its stack\nfatal runtime error: stack overflow\n";
+        assert_eq!(
+            describe_failure(&ExitStatus::from_raw(libc::SIGABRT), stderr).as_deref(),
+            Some("killed by SIGABRT (stack overflow)")
+        );
+
+        let stderr = "thread 'main' panicked at src/main.rs:1:2:\nboom\n";
+        assert_eq!(
+            describe_failure(&ExitStatus::from_raw(101 << 8), stderr).as_deref(),
+            Some("exit code 101 (panicked at src/main.rs:1:2: boom)")
+        );
+        assert_eq!(
+            describe_failure(&ExitStatus::from_raw(1 << 8), "").as_deref(),
+            Some("exit code 1")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn signal_names() {
+        use std::os::unix::process::ExitStatusExt;
+        let name = |raw| signal_name(&ExitStatus::from_raw(raw));
+        assert_eq!(name(libc::SIGSEGV).as_deref(), Some("SIGSEGV"));
+        assert_eq!(name(libc::SIGFPE).as_deref(), Some("SIGFPE"));
+        assert_eq!(name(libc::SIGKILL).as_deref(), Some("SIGKILL"));
+        assert_eq!(name(libc::SIGXCPU).as_deref(), Some("SIGXCPU"));
+        assert_eq!(
+            name(libc::SIGUSR1),
+            Some(format!("signal {}", libc::SIGUSR1))
+        );
+        // Exited normally, with or without an error code.
+        assert_eq!(name(0), None);
+        assert_eq!(name(1 << 8), None);
+    }
+}
@@ -0,0 +1,131 @@
+//! Login sessions saved by `kp login`.
+//!
+//! Sessions live in `session.json` in the kp config directory, keyed by the site's base URL,
+//! so a local mock server does not overwrite the real login. The file holds a password
+//! equivalent and is only readable by its owner.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Session {
+    /// Value of the `REVEL_SESSION` cookie.
+    pub session: String,
+    /// User name the session was logged in as.
+    pub user: String,
+    /// When the session was saved, in seconds since the Unix epoch.
+    pub saved_at: u64,
+}
+
+fn path() -> Result<PathBuf> {
+    let dir = crate::config_dir().context("cannot locate the kp config directory")?;
+    Ok(dir.join("session.json"))
+}
+
+fn read_all() -> Result<BTreeMap<String, Session>> {
+    let path = path()?;
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let json =
+        fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&json).with_context(|| format!("{} is not valid", path.display()))
+}
+
+fn write_all(sessions: &BTreeMap<String, Session>) -> Result<()> {
+    let path = path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let json = serde_json::to_string_pretty(sessions)?;
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        // `mode` only applies to new files; tighten one written by an older version too.
+        if path.exists() {
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    let mut file = options
+        .open(&path)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    std::io::Write::write_all(&mut file, json.as_bytes())?;
+    Ok(())
+}
+
+/// The saved session for `base_url`.
+pub fn load(base_url: &str) -> Result<Option<Session>> {
+    Ok(read_all()?.remove(base_url))
+}
+
+/// Save `session` for `base_url`, replacing any earlier one.
+pub fn save(base_url: &str, session: Session) -> Result<()> {
+    let mut sessions = read_all()?;
+    sessions.insert(base_url.to_string(), session);
+    write_all(&sessions)
+}
+
+/// Forget the session for `base_url`; returns whether there was one.
+pub fn remove(base_url: &str) -> Result<bool> {
+    let mut sessions = read_all()?;
+    let removed = sessions.remove(base_url).is_some();
+    if removed {
+        write_all(&sessions)?;
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_load_and_remove() {
+        // The only test that touches the config directory, so the variable is ours alone.
+        let dir = std::env::temp_dir().join(format!("kp-test-{}-session", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        std::env::set_var("KP_CONFIG_DIR", &dir);
+
+        // A file left readable by others is tightened on the next save.
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("session.json"), "{}").unwrap();
+
+        let site = "https://atcoder.jp";
+        let mock = "http://127.0.0.1:8080";
+        assert!(load(site).unwrap().is_none());
+        assert!(!remove(site).unwrap());
+        let session = |user: &str| Session {
+            session: format!("{user}-cookie"),
+            user: user.to_string(),
+            saved_at: 1_700_000_000,
+        };
+        save(site, session("alice")).unwrap();
+        save(mock, session("test")).unwrap();
+        save(site, session("bob")).unwrap();
+
+        let saved = load(site).unwrap().unwrap();
+        assert_eq!(
+            (saved.user.as_str(), saved.session.as_str()),
+            ("bob", "bob-cookie")
+        );
+        assert_eq!(saved.saved_at, 1_700_000_000);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(dir.join("session.json"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        assert!(remove(site).unwrap());
+        assert!(load(site).unwrap().is_none());
+        assert_eq!(load(mock).unwrap().unwrap().user, "test");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,519 @@
+//! Minimization of failing inputs found by `kp stress`.
+//!
+//! The shrinker repeatedly asks an [`InputFormat`] for smaller variants of the current
+//! input and keeps the first one that still fails, until no variant fails any more.
+//!
+//! Without a description the input is treated as free-form lines of tokens. A format
+//! description (`testcases/<problem>/format.txt`) lets lengths and arrays shrink together;
+//! each line of it describes the input the way problem statements do:
+//!
+//! ```text
+//! N M          a line of scalars; names can be used as lengths below
+//! A[N]         one line holding N tokens
+//! [M] U V      M lines, each holding the tokens U and V
+//! B[N-1]       lengths may be a name plus or minus a constant, or a number
+//! ```
+
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+
+/// Upper bound on the number of candidate inputs tried while minimizing.
+const MAX_ATTEMPTS: usize = 1000;
+
+/// A way of proposing smaller variants of an input.
+pub trait InputFormat {
+    /// Candidate inputs derived from `input`, the most aggressive reductions first.
+    fn candidates(&self, input: &str) -> Vec<String>;
+}
+
+/// Shrink `input` while `still_fails` holds.
+///
+/// `progress` is called with the current best input after every successful reduction.
+pub fn minimize(
+    input: &str,
+    format: &dyn InputFormat,
+    mut still_fails: impl FnMut(&str) -> Result<bool>,
+    mut progress: impl FnMut(&str),
+) -> Result<String> {
+    let mut current = input.to_string();
+    let mut attempts = 0;
+    'outer: loop {
+        for candidate in format.candidates(&current) {
+            if candidate == current {
+                continue;
+            }
+            if attempts >= MAX_ATTEMPTS {
+                break 'outer;
+            }
+            attempts += 1;
+            if still_fails(&candidate)? {
+                current = candidate;
+                progress(&current);
+                continue 'outer;
+            }
+        }
+        break;
+    }
+    Ok(current)
+}
+
+/// Treats the input as lines of whitespace separated tokens: drops lines and tokens and
+/// shrinks numbers, without knowing how they relate to each other.
+pub struct FreeForm;
+
+impl InputFormat for FreeForm {
+    fn candidates(&self, input: &str) -> Vec<String> {
+        let lines: Vec<Vec<String>> = input
+            .lines()
+            .map(|l| l.split_whitespace().map(str::to_string).collect())
+            .collect();
+        let mut out = Vec::new();
+
+        // Drop whole lines, last ones first since trailing data tends to be the bulk.
+        for i in (0..lines.len()).rev() {
+            let mut next = lines.clone();
+            next.remove(i);
+            out.push(render(&next));
+        }
+        // Drop single tokens from lines that have several.
+        for (i, line) in lines.iter().enumerate() {
+            if line.len() < 2 {
+                continue;
+            }
+            for j in (0..line.len()).rev() {
+                let mut next = lines.clone();
+                next[i].remove(j);
+                out.push(render(&next));
+            }
+        }
+        // Make numbers smaller.
+        for (i, line) in lines.iter().enumerate() {
+            for (j, token) in line.iter().enumerate() {
+                for smaller in smaller_numbers(token) {
+                    let mut next = lines.clone();
+                    next[i][j] = smaller;
+                    out.push(render(&next));
+                }
+            }
+        }
+        out
+    }
+}
+
+/// An input described by a format description (see the module documentation).
+pub struct Described {
+    blocks: Vec<Block>,
+}
+
+enum Block {
+    /// A line of named scalars.
+    Scalars(Vec<String>),
+    /// One line holding `len` tokens.
+    Array(Len),
+    /// `len` lines, each holding `width` tokens.
+    Rows(Len, usize),
+}
+
+#[derive(Clone)]
+enum Len {
+    Const(usize),
+    Var(String, i64),
+}
+
+/// Parsed values of an input following a [`Described`] format.
+#[derive(Clone)]
+struct Values {
+    scalars: HashMap<String, String>,
+    /// Token lines of each block, in block order.
+    blocks: Vec<Vec<Vec<String>>>,
+}
+
+impl Described {
+    pub fn parse(description: &str) -> Result<Described> {
+        let mut blocks = Vec::new();
+        for line in description.lines() {
+            let line = line.split('#').next().unwrap_or_default();
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            let Some(first) = tokens.first() else {
+                continue;
+            };
+            if let Some(len) = first.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+                if tokens.len() < 2 {
+                    bail!("`{}`: rows need at least one token name", line.trim());
+                }
+                blocks.push(Block::Rows(parse_len(len)?, tokens.len() - 1));
+            } else if let (1, Some(open)) = (tokens.len(), first.find('[')) {
+                let len = first[open + 1..]
+                    .strip_suffix(']')
+                    .ok_or_else(|| anyhow::anyhow!("`{}`: missing `]`", first))?;
+                blocks.push(Block::Array(parse_len(len)?));
+            } else if tokens.iter().any(|t| t.contains('[')) {
+                bail!("`{}`: an array must be alone on its line", line.trim());
+            } else {
+                blocks.push(Block::Scalars(
+                    tokens.iter().map(|t| t.to_string()).collect(),
+                ));
+            }
+        }
+        Ok(Described { blocks })
+    }
+
+    /// Split `input` according to the description, or `None` if it does not fit.
+    fn read(&self, input: &str) -> Option<Values> {
+        let mut lines = input.lines();
+        let mut values = Values {
+            scalars: HashMap::new(),
+            blocks: Vec::new(),
+        };
+        for block in &self.blocks {
+            let tokens =
+                |l: &str| -> Vec<String> { l.split_whitespace().map(str::to_string).collect() };
+            let block_lines = match block {
+                Block::Scalars(names) => {
+                    let line = tokens(lines.next()?);
+                    if line.len() != names.len() {
+                        return None;
+                    }
+                    for (name, value) in names.iter().zip(&line) {
+                        values.scalars.insert(name.clone(), value.clone());
+                    }
+                    vec![line]
+                }
+                Block::Array(len) => {
+                    let line = tokens(lines.next().unwrap_or_default());
+                    if line.len() != values.len(len)? {
+                        return None;
+                    }
+                    vec![line]
+                }
+                Block::Rows(len, width) => {
+                    let mut rows = Vec::new();
+                    for _ in 0..values.len(len)? {
+                        let row = tokens(lines.next()?);
+                        if row.len() != *width {
+                            return None;
+                        }
+                        rows.push(row);
+                    }
+                    rows
+                }
+            };
+            values.blocks.push(block_lines);
+        }
+        if lines.any(|l| !l.trim().is_empty()) {
+            return None;
+        }
+        Some(values)
+    }
+
+    fn write(&self, values: &Values) -> String {
+        let mut lines = Vec::new();
+        for (block, block_lines) in self.blocks.iter().zip(&values.blocks) {
+            match block {
+                Block::Scalars(names) => lines.push(
+                    names
+                        .iter()
+                        .map(|n| values.scalars[n].clone())
+                        .collect::<Vec<_>>(),
+                ),
+                _ => lines.extend(block_lines.iter().cloned()),
+            }
+        }
+        render(&lines)
+    }
+
+    /// Names used as lengths of arrays or rows.
+    fn length_vars(&self) -> Vec<String> {
+        let mut vars = Vec::new();
+        for block in &self.blocks {
+            if let Block::Array(Len::Var(name, _)) | Block::Rows(Len::Var(name, _), _) = block {
+                if !vars.contains(name) {
+                    vars.push(name.clone());
+                }
+            }
+        }
+        vars
+    }
+
+    /// Remove `count` elements starting at `start` from everything whose length is `var`.
+    fn remove_elements(
+        &self,
+        values: &Values,
+        var: &str,
+        start: usize,
+        count: usize,
+    ) -> Option<Values> {
+        let mut next = values.clone();
+        let n: i64 = next.scalars.get(var)?.parse().ok()?;
+        next.scalars
+            .insert(var.to_string(), (n - count as i64).to_string());
+        for (block, block_lines) in self.blocks.iter().zip(next.blocks.iter_mut()) {
+            match block {
+                // An array's elements are the tokens of its single line.
+                Block::Array(Len::Var(name, _)) if name == var => {
+                    drain_clamped(&mut block_lines[0], start, count)?
+                }
+                Block::Rows(Len::Var(name, _), _) if name == var => {
+                    drain_clamped(block_lines, start, count)?
+                }
+                _ => {}
+            }
+        }
+        Some(next)
+    }
+}
+
+impl Values {
+    fn len(&self, len: &Len) -> Option<usize> {
+        match len {
+            Len::Const(n) => Some(*n),
+            Len::Var(name, offset) => {
+                let value: i64 = self.scalars.get(name)?.parse().ok()?;
+                usize::try_from(value + offset).ok()
+            }
+        }
+    }
+}
+
+impl InputFormat for Described {
+    fn candidates(&self, input: &str) -> Vec<String> {
+        let Some(values) = self.read(input) else {
+            return FreeForm.candidates(input);
+        };
+        let mut out = Vec::new();
+        let length_vars = self.length_vars();
+
+        // Shorten arrays and rows together with their length: halves first, then one by one.
+        for var in &length_vars {
+            let Some(n) = values
+                .scalars
+                .get(var)
+                .and_then(|v| v.parse::<usize>().ok())
+            else {
+                continue;
+            };
+            let mut removals = Vec::new();
+            if n >= 2 {
+                removals.push((n / 2, n - n / 2));
+                removals.push((0, n / 2));
+            }
+            removals.extend((0..n).rev().map(|i| (i, 1)));
+            for (start, count) in removals {
+                if let Some(next) = self.remove_elements(&values, var, start, count) {
+                    out.push(self.write(&next));
+                }
+            }
+        }
+
+        // Make numbers smaller, leaving lengths alone.
+        let mut names: Vec<&String> = values.scalars.keys().collect();
+        names.sort();
+        for name in names {
+            if length_vars.contains(name) {
+                continue;
+            }
+            for smaller in smaller_numbers(&values.scalars[name]) {
+                let mut next = values.clone();
+                next.scalars.insert(name.clone(), smaller);
+                out.push(self.write(&next));
+            }
+        }
+        for (b, block) in self.blocks.iter().enumerate() {
+            if let Block::Scalars(_) = block {
+                continue;
+            }
+            for (i, line) in values.blocks[b].iter().enumerate() {
+                for (j, token) in line.iter().enumerate() {
+                    for smaller in smaller_numbers(token) {
+                        let mut next = values.clone();
+                        next.blocks[b][i][j] = smaller;
+                        out.push(self.write(&next));
+                    }
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Remove `count` items at `start`, moved left if needed so that exactly `count` go.
+fn drain_clamped<T>(items: &mut Vec<T>, start: usize, count: usize) -> Option<()> {
+    let from = start.min(items.len().checked_sub(count)?);
+    items.drain(from..from + count);
+    Some(())
+}
+
+fn parse_len(text: &str) -> Result<Len> {
+    if let Ok(n) = text.parse() {
+        return Ok(Len::Const(n));
+    }
+    let (name, offset) = match text.find(['+', '-']) {
+        Some(i) => {
+            let offset: i64 = text[i + 1..]
+                .parse()
+                .map_err(|_| anyhow::anyhow!("invalid length `{}`", text))?;
+            let sign = if text.as_bytes()[i] == b'-' { -1 } else { 1 };
+            (&text[..i], sign * offset)
+        }
+        None => (text, 0),
+    };
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        bail!("invalid length `{}`", text);
+    }
+    Ok(Len::Var(name.to_string(), offset))
+}
+
+/// Smaller integers to try in place of `token`, moving towards zero.
+///
+/// Positive numbers are kept positive since constraints usually start at 1.
+fn smaller_numbers(token: &str) -> Vec<String> {
+    let Ok(v) = token.parse::<i64>() else {
+        return Vec::new();
+    };
+    let mut out: Vec<i64> = if v > 1 {
+        vec![1, v / 2, v - 1]
+    } else if v < 0 {
+        vec![0, v / 2, v + 1]
+    } else {
+        Vec::new()
+    };
+    out.dedup();
+    out.retain(|&x| x != v);
+    out.into_iter().map(|x| x.to_string()).collect()
+}
+
+fn render(lines: &[Vec<String>]) -> String {
+    let mut out = String::new();
+    for line in lines {
+        out.push_str(&line.join(" "));
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(format: &Described, input: &str) -> Values {
+        format.read(input).expect("input fits the description")
+    }
+
+    #[test]
+    fn parse_rejects_malformed_descriptions() {
+        assert!(Described::parse("N\nA[N").is_err());
+        assert!(Described::parse("N A[N]").is_err());
+        assert!(Described::parse("[N]").is_err());
+        assert!(Described::parse("A[N*2]").is_err());
+        assert!(Described::parse("A[]").is_err());
+        assert!(Described::parse("A[N-x]").is_err());
+    }
+
+    #[test]
+    fn parse_skips_comments_and_blank_lines() {
+        let format = Described::parse("# header\nN M  # sizes\n\nA[N]\n[M] U V\nB[3]\n").unwrap();
+        assert_eq!(format.blocks.len(), 4);
+        assert_eq!(format.length_vars(), ["N", "M"]);
+    }
+
+    #[test]
+    fn read_and_write_round_trip() {
+        let format = Described::parse("N M\nA[N]\n[M] U V\nB[N-1]").unwrap();
+        let input = "3 2\n5 6 7\n1 2\n2 3\n8 9\n";
+        let values = values(&format, input);
+        assert_eq!(values.scalars["N"], "3");
+        assert_eq!(values.blocks[2], [["1", "2"], ["2", "3"]]);
+        assert_eq!(format.write(&values), input);
+    }
+
+    #[test]
+    fn read_rejects_inputs_that_do_not_fit() {
+        let format = Described::parse("N\nA[N]").unwrap();
+        assert!(format.read("3\n1 2\n").is_none());
+        assert!(format.read("3\n1 2 3\nextra\n").is_none());
+        assert!(format.read("x\n1 2 3\n").is_none());
+        assert!(format.read("2 3\n1 2\n").is_none());
+        // An empty array may be written as a missing line.
+        assert!(format.read("0\n").is_some());
+        assert!(format.read("3\n1 2 3\n\n").is_some());
+    }
+
+    #[test]
+    fn remove_elements_keeps_lengths_and_arrays_consistent() {
+        let format = Described::parse("N M\nA[N]\n[N] X Y\nB[N-1]\nC[M]").unwrap();
+        let input = "4 2\n1 2 3 4\n1 1\n2 2\n3 3\n4 4\n10 20 30\n7 8\n";
+        let values = values(&format, input);
+
+        let next = format.remove_elements(&values, "N", 1, 2).unwrap();
+        assert_eq!(format.write(&next), "2 2\n1 4\n1 1\n4 4\n10\n7 8\n");
+        // B is one shorter, so removing its last element moves the removal left.
+        let next = format.remove_elements(&values, "N", 3, 1).unwrap();
+        assert_eq!(
+            format.write(&next),
+            "3 2\n1 2 3\n1 1\n2 2\n3 3\n10 20\n7 8\n"
+        );
+        // Every candidate still fits the description.
+        for candidate in format.candidates(input) {
+            assert!(format.read(&candidate).is_some(), "{candidate:?}");
+        }
+    }
+
+    #[test]
+    fn remove_elements_fails_when_an_array_is_too_short() {
+        let format = Described::parse("N\nA[N]\nB[N-1]").unwrap();
+        let values = values(&format, "1\n5\n\n");
+        assert!(format.remove_elements(&values, "N", 0, 1).is_none());
+    }
+
+    #[test]
+    fn drain_clamped_moves_the_range_left() {
+        let mut items = vec![1, 2, 3, 4];
+        drain_clamped(&mut items, 3, 2).unwrap();
+        assert_eq!(items, [1, 2]);
+        let mut items = vec![1, 2, 3, 4];
+        drain_clamped(&mut items, 1, 2).unwrap();
+        assert_eq!(items, [1, 4]);
+        assert!(drain_clamped(&mut vec![1], 0, 2).is_none());
+    }
+
+    #[test]
+    fn smaller_numbers_move_towards_zero() {
+        assert_eq!(smaller_numbers("10"), ["1", "5", "9"]);
+        assert_eq!(smaller_numbers("2"), ["1"]);
+        assert_eq!(smaller_numbers("-6"), ["0", "-3", "-5"]);
+        assert!(smaller_numbers("1").is_empty());
+        assert!(smaller_numbers("0").is_empty());
+        assert!(smaller_numbers("abc").is_empty());
+    }
+
+    #[test]
+    fn minimize_shrinks_a_length_with_its_array() {
+        let format = Described::parse("N\nA[N]").unwrap();
+        let input = "6\n3 1 4 1 5 9\n";
+        // Fails as long as some element is at least 5.
+        let shrunk = minimize(
+            input,
+            &format,
+            |candidate| {
+                let values = format.read(candidate).expect("consistent candidate");
+                let n: usize = values.scalars["N"].parse().unwrap();
+                assert_eq!(values.blocks[1][0].len(), n);
+                Ok(values.blocks[1][0]
+                    .iter()
+                    .any(|a| a.parse::<i64>().unwrap() >= 5))
+            },
+            |_| {},
+        )
+        .unwrap();
+        assert_eq!(shrunk, "1\n5\n");
+    }
+
+    #[test]
+    fn free_form_drops_lines_and_tokens() {
+        let candidates = FreeForm.candidates("1 2\n3\n");
+        assert_eq!(candidates[0], "1 2\n");
+        assert_eq!(candidates[1], "3\n");
+        assert!(candidates.contains(&"1\n3\n".to_string()));
+        assert!(candidates.contains(&"1 1\n3\n".to_string()));
+    }
+}
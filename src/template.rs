@@ -0,0 +1,232 @@
+//! Contest workspace templates.
+//!
+//! Templates use the atcoder-cli layout: a directory holding `template.json` and the files
+//! it names. `kp init` installs the kp-rust template; without one a minimal built-in
+//! template is used.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::atcoder::TaskInfo;
+
+/// Name of the template directory installed by `kp init`.
+pub const TEMPLATE_NAME: &str = "kp-rust";
+
+/// `template.json`
+#[derive(Deserialize)]
+struct TemplateJson {
+    task: TaskTemplate,
+    #[serde(default)]
+    contest: ContestTemplate,
+}
+
+#[derive(Deserialize)]
+struct TaskTemplate {
+    /// Files copied for every task.
+    program: Vec<FileEntry>,
+    /// The file submitted for a task.
+    submit: String,
+    cmd: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct ContestTemplate {
+    /// Files copied once into the contest directory.
+    #[serde(default, rename = "static")]
+    files: Vec<FileEntry>,
+    cmd: Option<String>,
+}
+
+/// A template file, either copied under its own name or as `[source, destination]`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FileEntry {
+    Same(String),
+    Renamed(String, String),
+}
+
+impl FileEntry {
+    fn source(&self) -> &str {
+        match self {
+            FileEntry::Same(name) | FileEntry::Renamed(name, _) => name,
+        }
+    }
+
+    fn destination(&self) -> &str {
+        match self {
+            FileEntry::Same(name) | FileEntry::Renamed(_, name) => name,
+        }
+    }
+}
+
+pub struct Template {
+    json: TemplateJson,
+    /// Directory the files are copied from; `None` for the built-in template.
+    dir: Option<PathBuf>,
+}
+
+const BUILTIN_CARGO_TOML: &str = r#"[package]
+name = "{contestid}"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+"#;
+
+/// Empty, so that a task not started yet is judged WA rather than RE.
+const BUILTIN_MAIN_RS: &str = r#"fn main() {}
+"#;
+
+impl Template {
+    /// The installed template: the kp config directory first, then atcoder-cli's, then
+    /// the built-in one.
+    pub fn find() -> Result<Template> {
+        let candidates = [crate::config_dir(), atcoder_cli_config_dir()];
+        for dir in candidates.into_iter().flatten() {
+            let dir = dir.join(TEMPLATE_NAME);
+            let path = dir.join("template.json");
+            if path.is_file() {
+                let json = fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                let json = serde_json::from_str(&json)
+                    .with_context(|| format!("{} is not a valid template", path.display()))?;
+                return Ok(Template {
+                    json,
+                    dir: Some(dir),
+                });
+            }
+        }
+        Ok(Template {
+            json: TemplateJson {
+                task: TaskTemplate {
+                    program: vec![FileEntry::Renamed(
+                        "main.rs".to_string(),
+                        "src/bin/{tasklabel}.rs".to_string(),
+                    )],
+                    submit: "src/bin/{tasklabel}.rs".to_string(),
+                    cmd: None,
+                },
+                contest: ContestTemplate {
+                    files: vec![FileEntry::Same("Cargo.toml".to_string())],
+                    cmd: None,
+                },
+            },
+            dir: None,
+        })
+    }
+
+    /// Where the template comes from, for messages.
+    pub fn describe(&self) -> String {
+        match &self.dir {
+            Some(dir) => dir.display().to_string(),
+            None => "built-in template".to_string(),
+        }
+    }
+
+    /// Populate the contest directory `root` for `tasks`.
+    ///
+    /// Existing files are left alone, so tasks sharing a file name keep the first copy.
+    pub fn create(&self, root: &Path, contest: &str, tasks: &[TaskInfo]) -> Result<()> {
+        fs::create_dir_all(root)?;
+        for entry in &self.json.contest.files {
+            let destination = expand(entry.destination(), contest, None);
+            self.copy(entry.source(), &root.join(destination), contest, None)?;
+        }
+        for (index, task) in tasks.iter().enumerate() {
+            for entry in &self.json.task.program {
+                let destination = expand(entry.destination(), contest, Some((index, task)));
+                self.copy(
+                    entry.source(),
+                    &root.join(destination),
+                    contest,
+                    Some((index, task)),
+                )?;
+            }
+        }
+        let commands = [&self.json.contest.cmd, &self.json.task.cmd];
+        for cmd in commands.into_iter().flatten() {
+            println!("⚠️  template command not run: {cmd}");
+        }
+        Ok(())
+    }
+
+    /// Path of the submitted file of `task`, relative to the contest directory.
+    pub fn submit(&self, contest: &str, index: usize, task: &TaskInfo) -> String {
+        expand(&self.json.task.submit, contest, Some((index, task)))
+    }
+
+    fn copy(
+        &self,
+        source: &str,
+        destination: &Path,
+        contest: &str,
+        task: Option<(usize, &TaskInfo)>,
+    ) -> Result<()> {
+        if destination.exists() {
+            return Ok(());
+        }
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        match &self.dir {
+            Some(dir) => {
+                fs::copy(dir.join(source), destination).with_context(|| {
+                    format!(
+                        "Failed to copy template file {}",
+                        dir.join(source).display()
+                    )
+                })?;
+            }
+            None => {
+                let contents = match source {
+                    "Cargo.toml" => BUILTIN_CARGO_TOML,
+                    _ => BUILTIN_MAIN_RS,
+                };
+                fs::write(destination, expand(contents, contest, task))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Replace the atcoder-cli placeholders (`{tasklabel}`, `{ContestID}`, ...) in `pattern`.
+fn expand(pattern: &str, contest: &str, task: Option<(usize, &TaskInfo)>) -> String {
+    let mut out = pattern
+        .replace("{ContestID}", contest)
+        .replace("{contestid}", &contest.to_lowercase())
+        .replace("{CONTESTID}", &contest.to_uppercase());
+    if let Some((index, task)) = task {
+        out = out
+            .replace("{TaskID}", &task.id)
+            .replace("{TaskLabel}", &task.label)
+            .replace("{tasklabel}", &task.label.to_lowercase())
+            .replace("{TASKLABEL}", &task.label.to_uppercase())
+            .replace("{index0}", &index.to_string())
+            .replace("{index1}", &(index + 1).to_string());
+    }
+    out
+}
+
+/// Where atcoder-cli keeps its configuration and templates.
+fn atcoder_cli_config_dir() -> Option<PathBuf> {
+    if cfg!(windows) {
+        let appdata = std::env::var_os("APPDATA")?;
+        Some(
+            PathBuf::from(appdata)
+                .join("atcoder-cli-nodejs")
+                .join("Config"),
+        )
+    } else if cfg!(target_os = "macos") {
+        let home = std::env::var_os("HOME")?;
+        Some(PathBuf::from(home).join("Library/Preferences/atcoder-cli-nodejs"))
+    } else {
+        let config = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| Some(PathBuf::from(std::env::var_os("HOME")?).join(".config")))?;
+        Some(config.join("atcoder-cli-nodejs"))
+    }
+}